@@ -0,0 +1,84 @@
+use super::error::FretboardError;
+use super::fretboard::StringName;
+
+/// 变调夹 Capo clamped behind a fret, across every string or only some of them (partial capo).
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Capo {
+    fret : u8,
+    /// None when the capo covers every string
+    covered_strings : Option<Vec<StringName>>,
+}
+
+impl Capo {
+    pub fn full(fret : u8) -> Result<Capo, FretboardError> {
+        if fret == 0 {
+            return Err(FretboardError::InvalidCapoFret(fret));
+        }
+        Ok(Capo { fret, covered_strings: None })
+    }
+
+    /// A capo covering only `covered_strings`, like the popular one leaving string 6 open for a Drop D sound.
+    pub fn partial(fret : u8, covered_strings : Vec<StringName>) -> Result<Capo, FretboardError> {
+        if fret == 0 {
+            return Err(FretboardError::InvalidCapoFret(fret));
+        }
+        if let Some(string) = covered_strings.iter().find(|string| **string == 0) {
+            return Err(FretboardError::InvalidStringNumber(*string));
+        }
+        if covered_strings.is_empty() {
+            return Err(FretboardError::EmptyCapo);
+        }
+        Ok(Capo { fret, covered_strings: Some(covered_strings) })
+    }
+
+    pub fn fret(&self) -> u8 {
+        self.fret
+    }
+
+    /// None when the capo covers every string
+    pub fn covered_strings(&self) -> Option<&[StringName]> {
+        self.covered_strings.as_deref()
+    }
+
+    pub fn covers(&self, string : StringName) -> bool {
+        self.covered_strings.as_ref().is_none_or(|covered_strings| covered_strings.contains(&string))
+    }
+
+    /// The capo left on the strings below `string_cnt`, None if it covers none of them.
+    pub(super) fn on_string_cnt(&self, string_cnt : usize) -> Option<Capo> {
+        match &self.covered_strings {
+            None => Some(self.clone()),
+            Some(covered_strings) => {
+                let covered_strings : Vec<StringName> = covered_strings.iter()
+                    .filter(|string| (**string as usize) <= string_cnt)
+                    .cloned()
+                    .collect();
+                (!covered_strings.is_empty()).then_some(Capo { fret: self.fret, covered_strings: Some(covered_strings) })
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_capos() {
+        assert_eq!(Err(FretboardError::InvalidCapoFret(0)), Capo::full(0));
+        assert_eq!(Err(FretboardError::EmptyCapo), Capo::partial(2, vec![]));
+        assert_eq!(Err(FretboardError::InvalidStringNumber(0)), Capo::partial(2, vec![0, 1]));
+    }
+
+    #[test]
+    fn partial_capo_covers_its_strings() {
+        let capo = Capo::partial(2, vec![1, 2, 3, 4, 5]).unwrap();
+        assert!(capo.covers(5));
+        assert!(!capo.covers(6));
+        assert!(Capo::full(2).unwrap().covers(6));
+        assert_eq!(None, Capo::partial(2, vec![5]).unwrap().on_string_cnt(4));
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+use crate::music::error::MusicError;
+
+/// 指板错误 Errors of building or querying a fretboard
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum FretboardError {
+    /// Strings are numbered from 1, the highest pitched one.
+    InvalidStringNumber(u8),
+    /// The fretboard has no string of this number.
+    StringNotFound(u8),
+    /// The fret lies between the head and the nut of a short string, like frets 1 to 5 on a banjo's fifth string.
+    FretBeforeNut { string : u8, fret : u8 },
+    /// The fret lies between the nut and a capo covering the string.
+    FretBehindCapo { string : u8, fret : u8 },
+    /// The fret is too far from the nut to tell its note, more than 127 frets.
+    FretOutOfRange { string : u8, fret : u8 },
+    /// A capo sits behind fret 1 or higher, within the fretboard.
+    InvalidCapoFret(u8),
+    /// A partial capo covers at least one string.
+    EmptyCapo,
+    /// A tuning needs at least one string.
+    EmptyTuning,
+    Music(MusicError),
+}
+
+impl fmt::Display for FretboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FretboardError::InvalidStringNumber(string) => write!(f, "string number {string} is invalid, strings are numbered from 1"),
+            FretboardError::StringNotFound(string) => write!(f, "string of number {string} doesn't exist"),
+            FretboardError::FretBeforeNut { string, fret } => write!(f, "fret {fret} is before the nut of string {string}"),
+            FretboardError::FretBehindCapo { string, fret } => write!(f, "fret {fret} of string {string} is behind the capo"),
+            FretboardError::FretOutOfRange { string, fret } => write!(f, "fret {fret} of string {string} is too far from the nut"),
+            FretboardError::InvalidCapoFret(fret) => write!(f, "capo can not be put on fret {fret}"),
+            FretboardError::EmptyCapo => write!(f, "capo covers no string"),
+            FretboardError::EmptyTuning => write!(f, "tuning has no string"),
+            FretboardError::Music(music_error) => write!(f, "{music_error}"),
+        }
+    }
+}
+
+impl std::error::Error for FretboardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FretboardError::Music(music_error) => Some(music_error),
+            _ => None,
+        }
+    }
+}
+
+impl From<MusicError> for FretboardError {
+    fn from(music_error : MusicError) -> Self {
+        FretboardError::Music(music_error)
+    }
+}
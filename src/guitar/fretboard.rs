@@ -1,67 +1,340 @@
 use derive_getters::Getters;
-use crate::music::note::{Interval, NaturalNoteName::*};
-use crate::music::note::{Note};
-
-type StringName = u8;
-pub const ZERO_FRETS_STANDARD_TUNING : [(StringName, Note); 6] = [
-    (1, E.natural().on_octave(4)),
-    (2, B.natural().on_octave(3)),
-    (3, G.natural().on_octave(3)),
-    (4, D.natural().on_octave(3)),
-    (5, A.natural().on_octave(2)),
-    (6, E.natural().on_octave(2)),
-];
+use serde::{Deserialize, Serialize};
+use crate::music::note::{Note, NoteName};
+use super::capo::Capo;
+use super::error::FretboardError;
+use super::instrument::Instrument;
+use super::region::Region;
+use super::tuning::Tuning;
+
+pub type StringName = u8;
 
 /// When you play a note, you put your finger on a string and "behind" a fret, or lift your finger if it is the zeroth fret.
-#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Getters)]
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Getters, Serialize, Deserialize)]
 pub struct Point {
     on_string : StringName,
     behind_fret : u8
 }
 
 impl Point {
-    pub fn of(on_string : StringName, behind_fret : u8) -> Point {
+    pub fn of(on_string : StringName, behind_fret : u8) -> Result<Point, FretboardError> {
         if on_string == 0 {
-            panic!("0th String dosen't exist...")
+            return Err(FretboardError::InvalidStringNumber(on_string));
         }
 
-        Point { on_string: on_string, behind_fret: behind_fret }
+        Ok(Point { on_string, behind_fret })
     }
 }
 
 pub struct Fretboard {
-    zero_frets_tuning : Vec<(StringName, Note)>,
-    fret_bar_cnt : u8
+    /// String 1 is tuned to the first note of the tuning, string 2 to the second...
+    tuning : Tuning,
+    fret_bar_cnt : u8,
+    /// Strings whose nut sits on a fret rather than at the head, like the fifth string of a banjo starting at fret 5.
+    /// Their open note sounds at point 0, fretted notes keep the neck's fret numbers, frets up to the nut can not be played.
+    short_strings : Vec<(StringName, u8)>,
+    capo : Option<Capo>,
 }
 
 impl Fretboard {
     pub fn of_fret_cnt(fret_bar_cnt : u8) -> Fretboard {
-        Fretboard { zero_frets_tuning: Vec::from(ZERO_FRETS_STANDARD_TUNING), fret_bar_cnt: fret_bar_cnt }
+        Fretboard { tuning: Tuning::standard(), fret_bar_cnt, short_strings: Vec::new(), capo: None }
     }
 
     pub fn of_standard() -> Fretboard {
-        Fretboard { zero_frets_tuning: Vec::from(ZERO_FRETS_STANDARD_TUNING), fret_bar_cnt: 24 }
+        Fretboard { tuning: Tuning::standard(), fret_bar_cnt: 24, short_strings: Vec::new(), capo: None }
+    }
+
+    pub fn of_tuning(tuning : Tuning, fret_bar_cnt : u8) -> Fretboard {
+        Fretboard { tuning, fret_bar_cnt, short_strings: Vec::new(), capo: None }
+    }
+
+    pub fn of_instrument(instrument : &Instrument, fret_bar_cnt : u8) -> Fretboard {
+        Fretboard { tuning: instrument.tuning(), fret_bar_cnt, short_strings: instrument.short_strings().to_vec(), capo: None }
+    }
+
+    /// Same fretboard with another tuning; short strings and the capo are kept on the strings still existing.
+    pub fn with_tuning(&self, tuning : Tuning) -> Fretboard {
+        let string_cnt = tuning.string_cnt();
+        let short_strings = self.short_strings.iter()
+            .filter(|(string, _)| (*string as usize) <= string_cnt)
+            .cloned()
+            .collect();
+        let capo = self.capo.as_ref().and_then(|capo| capo.on_string_cnt(string_cnt));
+        Fretboard { tuning, fret_bar_cnt: self.fret_bar_cnt, short_strings, capo }
+    }
+
+    /// Same fretboard with another fret count; the capo is taken off if no longer on it.
+    pub fn with_fret_bar_cnt(&self, fret_bar_cnt : u8) -> Fretboard {
+        let capo = self.capo.clone().filter(|capo| capo.fret() < fret_bar_cnt);
+        Fretboard { tuning: self.tuning.clone(), fret_bar_cnt, short_strings: self.short_strings.clone(), capo }
+    }
+
+    /// Same fretboard with the capo put on, moved or, given None, taken off.
+    pub fn with_capo(&self, capo : Option<Capo>) -> Result<Fretboard, FretboardError> {
+        if let Some(capo) = &capo {
+            if capo.fret() >= self.fret_bar_cnt {
+                return Err(FretboardError::InvalidCapoFret(capo.fret()));
+            }
+            if let Some(string) = capo.covered_strings().into_iter().flatten()
+                .find(|string| (**string as usize) > self.tuning.string_cnt()) {
+                return Err(FretboardError::StringNotFound(*string));
+            }
+        }
+        Ok(Fretboard { tuning: self.tuning.clone(), fret_bar_cnt: self.fret_bar_cnt, short_strings: self.short_strings.clone(), capo })
+    }
+
+    pub fn capo(&self) -> Option<&Capo> {
+        self.capo.as_ref()
+    }
+
+    /// The fret of the capo if it covers the string, 0 otherwise.
+    pub fn capo_fret_of_string(&self, string : StringName) -> u8 {
+        self.capo.as_ref()
+            .filter(|capo| capo.covers(string))
+            .map_or(0, Capo::fret)
+    }
+
+    /// The note the string sounds unfretted, raised by the capo if it covers the string.
+    pub fn open_note_of_string(&self, string : StringName) -> Result<Note, FretboardError> {
+        self.note_of_point(&Point::of(string, 0)?)
+    }
+
+    /// The fret counted from the capo, or from the nut on strings it doesn't cover, e.g. fret 5 is the second fret with a capo on 3.
+    /// Frets behind the capo have no capo-relative position.
+    pub fn point_relative_to_capo(&self, point : &Point) -> Option<Point> {
+        let capo_fret = self.capo_fret_of_string(point.on_string);
+        match point.behind_fret {
+            0 => Some(*point),
+            fret if fret > capo_fret => Some(Point { on_string: point.on_string, behind_fret: fret - capo_fret }),
+            _ => None,
+        }
+    }
+
+    /// Inverse of `point_relative_to_capo`.
+    pub fn point_of_capo_relative(&self, capo_relative_point : &Point) -> Point {
+        match capo_relative_point.behind_fret {
+            0 => *capo_relative_point,
+            fret => Point {
+                on_string: capo_relative_point.on_string,
+                behind_fret: fret.saturating_add(self.capo_fret_of_string(capo_relative_point.on_string)),
+            },
+        }
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    /// The fret where the string's nut sits, 0 for a full length string.
+    pub fn nut_fret_of_string(&self, string : StringName) -> u8 {
+        self.short_strings.iter()
+            .find(|(short_string, _)| *short_string == string)
+            .map_or(0, |(_, nut_fret)| *nut_fret)
+    }
+
+    /// Whether the point exists on this fretboard and can be played.
+    pub fn contains(&self, point : &Point) -> bool {
+        (point.on_string as usize) <= self.tuning.string_cnt()
+            && point.behind_fret < self.fret_bar_cnt
+            && (point.behind_fret == 0 || point.behind_fret > self.first_stopped_fret_of_string(point.on_string))
     }
 
     pub fn string_name_vec(&self) -> Vec<StringName> {
-        self.zero_frets_tuning.iter().map(|string_tuning| { string_tuning.0 }).collect()
+        (1..=self.tuning.string_cnt() as StringName).collect()
     }
 
     pub fn fret_bar_cnt(&self) -> u8 {
         self.fret_bar_cnt
     }
 
-    pub fn note_of_point(&self, point : &Point) -> Note {
-        self.zero_fret_note_of_string(point.on_string)
-            .add_interval(&Interval::of_semitone_diff(point.behind_fret as i8))
+    /// Every point on every string, from the zeroth fret to the last one.
+    pub fn points(&self) -> Vec<Point> {
+        self.string_name_vec().into_iter()
+            .flat_map(|on_string| (0..self.fret_bar_cnt).map(move |behind_fret| Point { on_string, behind_fret }))
+            .filter(|point| self.contains(point))
+            .collect()
     }
 
-    fn zero_fret_note_of_string(&self, string : StringName) -> Note {
-        for string_zero_fret_note in &self.zero_frets_tuning {
-            if string_zero_fret_note.0 == string {
-                return string_zero_fret_note.1.clone()
-            }
+    /// The points of the region, in the order of `points`
+    pub fn points_in(&self, region : &Region) -> Vec<Point> {
+        self.points().into_iter()
+            .filter(|point| region.contains(self, point))
+            .collect()
+    }
+
+    /// Point 0 is the string sounding unfretted, from the capo if it covers the string.
+    pub fn note_of_point(&self, point : &Point) -> Result<Note, FretboardError> {
+        let zero_fret_note = self.zero_fret_note_of_string(point.on_string)?;
+        let nut_fret = self.nut_fret_of_string(point.on_string);
+        let capo_fret = self.capo_fret_of_string(point.on_string);
+        let sounding_fret = match point.behind_fret {
+            0 => nut_fret.max(capo_fret),
+            fret if fret <= nut_fret => return Err(FretboardError::FretBeforeNut { string: point.on_string, fret }),
+            fret if fret <= capo_fret => return Err(FretboardError::FretBehindCapo { string: point.on_string, fret }),
+            fret => fret,
+        };
+        let semitone_diff = i8::try_from(sounding_fret - nut_fret)
+            .map_err(|_| FretboardError::FretOutOfRange { string: point.on_string, fret: point.behind_fret })?;
+        Ok(zero_fret_note.add_semitones(semitone_diff)?)
+    }
+
+    /// The fret a point sounds at: an open string at its nut or at the capo covering it, e.g. 2 for point 0 under a capo on 2.
+    pub fn sounding_fret_of_point(&self, point : &Point) -> u8 {
+        match point.behind_fret {
+            0 => self.first_stopped_fret_of_string(point.on_string),
+            fret => fret,
         }
-        panic!("String of number {string} dosen't exist...")
     }
-}
\ No newline at end of file
+
+    /// 同音位置 Every point sounding exactly this note, octave included, whatever its spelling.
+    pub fn positions_of_note(&self, note : &Note) -> Vec<Point> {
+        self.points().into_iter()
+            .filter(|point| self.note_of_point(point).is_ok_and(|point_note| point_note == *note))
+            .collect()
+    }
+
+    /// 同名音位置 Every point sounding this pitch class, in any octave, e.g. all the Cs.
+    pub fn positions_of_pitch_class(&self, note_name : &NoteName) -> Vec<Point> {
+        self.positions_of_pitch_class_in(note_name, &Region::whole())
+    }
+
+    /// The positions of the pitch class in the region only
+    pub fn positions_of_pitch_class_in(&self, note_name : &NoteName, region : &Region) -> Vec<Point> {
+        self.points_in(region).into_iter()
+            .filter(|point| self.note_of_point(point).is_ok_and(|point_note| point_note.note_name() == *note_name))
+            .collect()
+    }
+
+    /// Frets up to this one can not be pressed, because of a short string's nut or the capo.
+    fn first_stopped_fret_of_string(&self, string : StringName) -> u8 {
+        self.nut_fret_of_string(string).max(self.capo_fret_of_string(string))
+    }
+
+    fn zero_fret_note_of_string(&self, string : StringName) -> Result<Note, FretboardError> {
+        (string as usize).checked_sub(1)
+            .and_then(|string_index| self.tuning.zero_fret_notes().get(string_index))
+            .cloned()
+            .ok_or(FretboardError::StringNotFound(string))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::error::MusicError;
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    #[test]
+    fn invalid_strings() {
+        assert_eq!(Err(FretboardError::InvalidStringNumber(0)), Point::of(0, 3));
+        assert_eq!(Err(FretboardError::StringNotFound(7)),
+            Fretboard::of_standard().note_of_point(&Point::of(7, 3).unwrap()));
+    }
+
+    #[test]
+    fn note_of_point() {
+        assert_eq!(Ok(C.natural().on_octave(4)), Fretboard::of_standard().note_of_point(&Point::of(2, 1).unwrap()));
+    }
+
+    #[test]
+    fn banjo_short_fifth_string() {
+        let fretboard = Fretboard::of_instrument(Instrument::by_name("5-String Banjo").unwrap(), 22);
+        assert_eq!(Ok(G.natural().on_octave(4)), fretboard.note_of_point(&Point::of(5, 0).unwrap()));
+        assert_eq!(Ok(A.natural().on_octave(4)), fretboard.note_of_point(&Point::of(5, 7).unwrap()));
+        assert_eq!(Err(FretboardError::FretBeforeNut { string: 5, fret: 3 }), fretboard.note_of_point(&Point::of(5, 3).unwrap()));
+        assert!(!fretboard.points().contains(&Point::of(5, 5).unwrap()));
+        assert!(fretboard.points().contains(&Point::of(4, 5).unwrap()));
+    }
+
+    #[test]
+    fn re_entrant_ukulele() {
+        let fretboard = Fretboard::of_instrument(Instrument::by_name("Ukulele").unwrap(), 15);
+        assert_eq!(vec![1, 2, 3, 4], fretboard.string_name_vec());
+        assert_eq!(Ok(G.natural().on_octave(4)), fretboard.note_of_point(&Point::of(4, 0).unwrap()));
+    }
+
+    #[test]
+    fn full_capo() {
+        let fretboard = Fretboard::of_standard().with_capo(Some(Capo::full(3).unwrap())).unwrap();
+        assert_eq!(Ok(G.natural().on_octave(4)), fretboard.open_note_of_string(1));
+        assert_eq!(Ok(A.natural().on_octave(4)), fretboard.note_of_point(&Point::of(1, 5).unwrap()));
+        assert_eq!(Err(FretboardError::FretBehindCapo { string: 1, fret: 2 }), fretboard.note_of_point(&Point::of(1, 2).unwrap()));
+        assert!(!fretboard.points().contains(&Point::of(6, 3).unwrap()));
+        assert_eq!(Some(Point::of(1, 2).unwrap()), fretboard.point_relative_to_capo(&Point::of(1, 5).unwrap()));
+        assert_eq!(Point::of(1, 5).unwrap(), fretboard.point_of_capo_relative(&Point::of(1, 2).unwrap()));
+        assert_eq!(None, fretboard.point_relative_to_capo(&Point::of(1, 3).unwrap()));
+    }
+
+    #[test]
+    fn partial_capo() {
+        let fretboard = Fretboard::of_standard().with_capo(Some(Capo::partial(2, vec![1, 2, 3, 4, 5]).unwrap())).unwrap();
+        assert_eq!(Ok(E.natural().on_octave(2)), fretboard.open_note_of_string(6));
+        assert_eq!(Ok(F.sharp().on_octave(2)), fretboard.note_of_point(&Point::of(6, 2).unwrap()));
+        assert_eq!(Ok(B.natural().on_octave(2)), fretboard.open_note_of_string(5));
+        assert_eq!(Err(FretboardError::StringNotFound(7)),
+            Fretboard::of_standard().with_capo(Some(Capo::partial(2, vec![7]).unwrap())).map(|_| ()));
+        assert_eq!(Err(FretboardError::InvalidCapoFret(24)),
+            Fretboard::of_standard().with_capo(Some(Capo::full(24).unwrap())).map(|_| ()));
+    }
+
+    #[test]
+    fn capo_past_banjo_fifth_string_nut() {
+        let banjo = Fretboard::of_instrument(Instrument::by_name("5-String Banjo").unwrap(), 22);
+        let fretboard = banjo.with_capo(Some(Capo::full(7).unwrap())).unwrap();
+        assert_eq!(Ok(A.natural().on_octave(4)), fretboard.open_note_of_string(5));
+        assert_eq!(Ok(B.natural().on_octave(4)), fretboard.note_of_point(&Point::of(5, 9).unwrap()));
+    }
+
+    #[test]
+    fn positions_of_note() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let points = |pairs : &[(u8, u8)]| -> Vec<Point> { pairs.iter().map(|(s, f)| Point::of(*s, *f).unwrap()).collect() };
+        assert_eq!(points(&[(2, 1), (3, 5), (4, 10)]), fretboard.positions_of_note(&C.natural().on_octave(4)));
+        assert_eq!(points(&[(1, 0), (2, 5), (3, 9)]), fretboard.positions_of_note(&F.flat().on_octave(4)));
+        assert!(fretboard.positions_of_note(&C.natural().on_octave(1)).is_empty());
+    }
+
+    #[test]
+    fn positions_of_pitch_class() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let positions = fretboard.positions_of_pitch_class(&C.natural());
+        assert_eq!(6, positions.len());
+        assert!(positions.contains(&Point::of(5, 3).unwrap()));
+        assert!(positions.contains(&Point::of(6, 8).unwrap()));
+        assert_eq!(positions, fretboard.positions_of_pitch_class(&B.sharp()));
+    }
+
+    #[test]
+    fn positions_of_pitch_class_in_region() {
+        let fretboard = Fretboard::of_standard();
+        let region = Region::of(5..=9, vec![4, 5, 6]);
+        assert_eq!(vec![Point::of(1, 8).unwrap(), Point::of(3, 5).unwrap()], fretboard.positions_of_pitch_class_in(&C.natural(), &region));
+        assert_eq!(15, fretboard.points_in(&region).len());
+    }
+
+    #[test]
+    fn high_octave_tuning() {
+        let notes = vec![E.natural().on_octave(11), B.natural().on_octave(3), G.natural().on_octave(3),
+                         D.natural().on_octave(3), A.natural().on_octave(2), E.natural().on_octave(255)];
+        let fretboard = Fretboard::of_tuning(Tuning::of("High", notes).unwrap(), 24);
+        assert_eq!(Ok(F.natural().on_octave(11)), fretboard.note_of_point(&Point::of(1, 1).unwrap()));
+        assert_eq!(Ok(B.natural().on_octave(255)), fretboard.note_of_point(&Point::of(6, 7).unwrap()));
+        assert_eq!(Err(FretboardError::Music(MusicError::OctaveOverflow)), fretboard.note_of_point(&Point::of(6, 8).unwrap()));
+    }
+
+    #[test]
+    fn frets_far_from_the_nut() {
+        let fretboard = Fretboard::of_fret_cnt(200);
+        assert_eq!(Ok(B.natural().on_octave(14)), fretboard.note_of_point(&Point::of(1, 127).unwrap()));
+        assert_eq!(Err(FretboardError::FretOutOfRange { string: 1, fret: 128 }), fretboard.note_of_point(&Point::of(1, 128).unwrap()));
+    }
+
+    #[test]
+    fn note_of_point_in_drop_d() {
+        let fretboard = Fretboard::of_tuning(Tuning::preset("Drop D").unwrap(), 12);
+        assert_eq!(Ok(E.natural().on_octave(2)), fretboard.note_of_point(&Point::of(6, 2).unwrap()));
+        assert_eq!(Ok(A.natural().on_octave(2)), fretboard.note_of_point(&Point::of(5, 0).unwrap()));
+    }
+}
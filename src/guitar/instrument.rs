@@ -0,0 +1,102 @@
+use crate::music::note::NaturalNoteName::*;
+use super::fretboard::StringName;
+use super::tuning::{Tuning, TuningPreset, TUNING_PRESETS};
+
+/// 乐器 A fretted instrument: its tunings, strings listed from string 1, and its short strings if any.
+pub struct Instrument {
+    name : &'static str,
+    /// Tunings offered for the instrument, its usual one first
+    tuning_presets : &'static [TuningPreset],
+    /// (string, fret the string's nut sits on)
+    short_strings : &'static [(StringName, u8)],
+}
+
+impl Instrument {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The usual tuning
+    pub fn tuning(&self) -> Tuning {
+        self.tuning_presets[0].tuning()
+    }
+
+    /// Tunings offered for the instrument, its usual one first
+    pub fn tuning_presets(&self) -> &'static [TuningPreset] {
+        self.tuning_presets
+    }
+
+    pub fn string_cnt(&self) -> usize {
+        self.tuning_presets[0].zero_fret_notes().len()
+    }
+
+    pub fn short_strings(&self) -> &'static [(StringName, u8)] {
+        self.short_strings
+    }
+
+    /// Looks up the presets by name, ignoring case.
+    pub fn by_name(name : &str) -> Option<&'static Instrument> {
+        INSTRUMENTS.iter().find(|instrument| instrument.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// 常用乐器 Instrument presets, the six-string guitar first
+pub static INSTRUMENTS : &[Instrument] = &[
+    Instrument { name: "Guitar", short_strings: &[], tuning_presets: TUNING_PRESETS },
+    Instrument { name: "7-String Guitar", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), E.natural().on_octave(2),
+        B.natural().on_octave(1)])] },
+    Instrument { name: "8-String Guitar", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), E.natural().on_octave(2),
+        B.natural().on_octave(1), F.sharp().on_octave(1)])] },
+    Instrument { name: "4-String Bass", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        G.natural().on_octave(2), D.natural().on_octave(2), A.natural().on_octave(1), E.natural().on_octave(1)])] },
+    Instrument { name: "5-String Bass", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        G.natural().on_octave(2), D.natural().on_octave(2), A.natural().on_octave(1), E.natural().on_octave(1),
+        B.natural().on_octave(0)])] },
+    Instrument { name: "6-String Bass", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        C.natural().on_octave(3), G.natural().on_octave(2), D.natural().on_octave(2), A.natural().on_octave(1),
+        E.natural().on_octave(1), B.natural().on_octave(0)])] },
+    // Re-entrant: string 4 is tuned above string 3
+    Instrument { name: "Ukulele", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        A.natural().on_octave(4), E.natural().on_octave(4), C.natural().on_octave(4), G.natural().on_octave(4)])] },
+    Instrument { name: "Mandolin", short_strings: &[], tuning_presets: &[TuningPreset::of("Standard", &[
+        E.natural().on_octave(5), A.natural().on_octave(4), D.natural().on_octave(4), G.natural().on_octave(3)])] },
+    // Open G, the short fifth string starts at fret 5
+    Instrument { name: "5-String Banjo", short_strings: &[(5, 5)], tuning_presets: &[TuningPreset::of("Open G", &[
+        D.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3), D.natural().on_octave(3),
+        G.natural().on_octave(4)])] },
+];
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_cnts() {
+        let string_cnts : Vec<usize> = INSTRUMENTS.iter().map(Instrument::string_cnt).collect();
+        assert_eq!(vec![6, 7, 8, 4, 5, 6, 4, 4, 5], string_cnts);
+    }
+
+    #[test]
+    fn guitar_is_standard_tuning() {
+        assert_eq!(Tuning::standard().zero_fret_notes(), Instrument::by_name("guitar").unwrap().tuning().zero_fret_notes());
+    }
+
+    #[test]
+    fn presets_fit_the_strings() {
+        for instrument in INSTRUMENTS {
+            assert!(instrument.tuning_presets().iter().all(|preset| preset.zero_fret_notes().len() == instrument.string_cnt()), "{}", instrument.name());
+        }
+    }
+
+    #[test]
+    fn short_strings_exist() {
+        for instrument in INSTRUMENTS {
+            assert!(instrument.short_strings().iter().all(|(string, _)| (*string as usize) <= instrument.string_cnt()), "{}", instrument.name());
+        }
+    }
+}
@@ -1 +1,6 @@
-pub mod fretboard;
\ No newline at end of file
+pub mod fretboard;
+pub mod tuning;
+pub mod instrument;
+pub mod capo;
+pub mod error;
+pub mod region;
\ No newline at end of file
@@ -0,0 +1,97 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use super::fretboard::{Fretboard, Point, StringName};
+
+/// 区域 Frets and strings to focus on, e.g. frets 5 to 9 on strings 1 to 3
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Region {
+    lowest_fret : u8,
+    /// u8::MAX for up to the last fret, however many there are
+    highest_fret : u8,
+    /// Strings left out, so that strings added by another instrument are in
+    skipped_strings : Vec<StringName>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::whole()
+    }
+}
+
+impl Region {
+    /// Every fret of every string
+    pub fn whole() -> Region {
+        Region { lowest_fret: 0, highest_fret: u8::MAX, skipped_strings: Vec::new() }
+    }
+
+    /// An empty fret range is taken as its lowest fret only.
+    pub fn of(frets : RangeInclusive<u8>, skipped_strings : Vec<StringName>) -> Region {
+        let (lowest_fret, highest_fret) = frets.into_inner();
+        Region { lowest_fret, highest_fret: highest_fret.max(lowest_fret), skipped_strings }
+    }
+
+    pub fn of_frets(frets : RangeInclusive<u8>) -> Region {
+        Region::of(frets, Vec::new())
+    }
+
+    pub fn frets(&self) -> RangeInclusive<u8> {
+        self.lowest_fret..=self.highest_fret
+    }
+
+    pub fn skipped_strings(&self) -> &[StringName] {
+        &self.skipped_strings
+    }
+
+    pub fn includes_string(&self, string : StringName) -> bool {
+        !self.skipped_strings.contains(&string)
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.lowest_fret == 0 && self.highest_fret == u8::MAX && self.skipped_strings.is_empty()
+    }
+
+    /// Whether the point is on an included string and sounds within the frets, open strings counting as their nut or capo fret.
+    pub fn contains(&self, fretboard : &Fretboard, point : &Point) -> bool {
+        self.includes_string(*point.on_string()) && self.frets().contains(&fretboard.sounding_fret_of_point(point))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::guitar::capo::Capo;
+    use super::*;
+
+    fn point(on_string : u8, behind_fret : u8) -> Point {
+        Point::of(on_string, behind_fret).unwrap()
+    }
+
+    #[test]
+    fn frets_and_strings() {
+        let fretboard = Fretboard::of_standard();
+        let region = Region::of(5..=9, vec![4, 5, 6]);
+        assert!(region.contains(&fretboard, &point(1, 5)));
+        assert!(region.contains(&fretboard, &point(3, 9)));
+        assert!(!region.contains(&fretboard, &point(3, 10)));
+        assert!(!region.contains(&fretboard, &point(4, 7)));
+        assert!(!region.contains(&fretboard, &point(1, 0)));
+        assert!(Region::whole().contains(&fretboard, &point(6, 23)));
+    }
+
+    #[test]
+    fn open_strings_sound_at_the_capo() {
+        let fretboard = Fretboard::of_standard().with_capo(Some(Capo::full(5).unwrap())).unwrap();
+        assert!(Region::of_frets(5..=9).contains(&fretboard, &point(2, 0)));
+    }
+
+    #[test]
+    fn empty_fret_range() {
+        let (lowest_fret, highest_fret) = (7, 3);
+        assert_eq!(7..=7, Region::of_frets(lowest_fret..=highest_fret).frets());
+    }
+}
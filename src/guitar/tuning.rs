@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use crate::music::error::MusicError;
+use crate::music::note::Note;
+use crate::music::note::NaturalNoteName::*;
+use super::error::FretboardError;
+
+/// 定弦 Tuning: the open (zeroth fret) note of every string, string 1, the highest pitched, first.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Tuning {
+    name : String,
+    zero_fret_notes : Vec<Note>,
+}
+
+/// A named tuning known in advance, strings listed from string 1.
+pub struct TuningPreset {
+    name : &'static str,
+    zero_fret_notes : &'static [Note],
+}
+
+impl TuningPreset {
+    pub const fn of(name : &'static str, zero_fret_notes : &'static [Note]) -> TuningPreset {
+        TuningPreset { name, zero_fret_notes }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Open notes from string 1, the highest pitched.
+    pub fn zero_fret_notes(&self) -> &'static [Note] {
+        self.zero_fret_notes
+    }
+
+    pub fn tuning(&self) -> Tuning {
+        Tuning { name: self.name.to_owned(), zero_fret_notes: self.zero_fret_notes.to_vec() }
+    }
+}
+
+/// 常用定弦 Six-string guitar tunings, the presets of `Instrument` "Guitar"
+pub static TUNING_PRESETS : &[TuningPreset] = &[
+    TuningPreset { name: "Standard", zero_fret_notes: &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), E.natural().on_octave(2)] },
+    TuningPreset { name: "Drop D", zero_fret_notes: &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), D.natural().on_octave(2)] },
+    TuningPreset { name: "DADGAD", zero_fret_notes: &[
+        D.natural().on_octave(4), A.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), D.natural().on_octave(2)] },
+    TuningPreset { name: "Open G", zero_fret_notes: &[
+        D.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(3),
+        D.natural().on_octave(3), G.natural().on_octave(2), D.natural().on_octave(2)] },
+    TuningPreset { name: "Open D", zero_fret_notes: &[
+        D.natural().on_octave(4), A.natural().on_octave(3), F.sharp().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), D.natural().on_octave(2)] },
+    TuningPreset { name: "Open E", zero_fret_notes: &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.sharp().on_octave(3),
+        E.natural().on_octave(3), B.natural().on_octave(2), E.natural().on_octave(2)] },
+    TuningPreset { name: "Half-Step Down", zero_fret_notes: &[
+        E.flat().on_octave(4), B.flat().on_octave(3), G.flat().on_octave(3),
+        D.flat().on_octave(3), A.flat().on_octave(2), E.flat().on_octave(2)] },
+    TuningPreset { name: "C Standard", zero_fret_notes: &[
+        C.natural().on_octave(4), G.natural().on_octave(3), E.flat().on_octave(3),
+        B.flat().on_octave(2), F.natural().on_octave(2), C.natural().on_octave(2)] },
+    TuningPreset { name: "Nashville High-Strung", zero_fret_notes: &[
+        E.natural().on_octave(4), B.natural().on_octave(3), G.natural().on_octave(4),
+        D.natural().on_octave(4), A.natural().on_octave(3), E.natural().on_octave(3)] },
+    TuningPreset { name: "All Fourths", zero_fret_notes: &[
+        F.natural().on_octave(4), C.natural().on_octave(4), G.natural().on_octave(3),
+        D.natural().on_octave(3), A.natural().on_octave(2), E.natural().on_octave(2)] },
+];
+
+impl Tuning {
+    /// `zero_fret_notes` start from string 1, the highest pitched.
+    pub fn of(name : &str, zero_fret_notes : Vec<Note>) -> Result<Tuning, FretboardError> {
+        if zero_fret_notes.is_empty() {
+            return Err(FretboardError::EmptyTuning);
+        }
+        Ok(Tuning { name: name.to_owned(), zero_fret_notes })
+    }
+
+    pub fn standard() -> Tuning {
+        TUNING_PRESETS[0].tuning()
+    }
+
+    /// Looks up the presets by name, ignoring case.
+    pub fn preset(name : &str) -> Option<Tuning> {
+        TUNING_PRESETS.iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+            .map(TuningPreset::tuning)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Open notes from string 1, the highest pitched.
+    pub fn zero_fret_notes(&self) -> &[Note] {
+        &self.zero_fret_notes
+    }
+
+    pub fn string_cnt(&self) -> usize {
+        self.zero_fret_notes.len()
+    }
+
+    /// Written the way players do, from the lowest pitched string up, e.g. "E2 A2 D3 G3 B3 E4".
+    pub fn string_representation(&self) -> String {
+        self.zero_fret_notes.iter().rev()
+            .map(|note| note.clone().string_representation())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Parses a user tuning written from the lowest pitched string up, e.g. "D2 A2 D3 G3 A3 D4".
+impl FromStr for Tuning {
+    type Err = FretboardError;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let mut zero_fret_notes = s.split_whitespace()
+            .map(str::parse::<Note>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(MusicError::from)?;
+        zero_fret_notes.reverse();
+        Tuning::of("Custom", zero_fret_notes)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_low_to_high() {
+        let tuning : Tuning = "D2 A2 D3 G3 A3 D4".parse().unwrap();
+        assert_eq!(Tuning::preset("dadgad").unwrap().zero_fret_notes(), tuning.zero_fret_notes());
+        assert_eq!("D2 A2 D3 G3 A3 D4", tuning.string_representation());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(FretboardError::EmptyTuning), "  ".parse::<Tuning>());
+        assert!(matches!("E2 H2".parse::<Tuning>(), Err(FretboardError::Music(MusicError::Parse(_)))));
+        assert!(matches!("E2 A2 D3 G3 B3 E11".parse::<Tuning>(), Err(FretboardError::Music(MusicError::Parse(_)))));
+    }
+
+    #[test]
+    fn presets_are_six_strings_high_to_low() {
+        for preset in TUNING_PRESETS {
+            let notes = preset.tuning().zero_fret_notes().to_vec();
+            assert_eq!(6, notes.len(), "{}", preset.name());
+            if preset.name() != "Nashville High-Strung" {
+                assert!(notes.windows(2).all(|pair| pair[0].minus_note(&pair[1]).semitone_diff() > 0), "{}", preset.name());
+            }
+        }
+    }
+}
@@ -10,5 +10,5 @@ use crate::ui::window::{FretboardApp};
 fn main() {
     let win_option = NativeOptions::default();
     let _ = run_native("Guitar App", win_option, 
-        Box::new(|_cc| Ok(Box::new(FretboardApp::new()))));
+        Box::new(|cc| Ok(Box::new(FretboardApp::of_storage(cc.storage)))));
 }
\ No newline at end of file
@@ -0,0 +1,343 @@
+use super::error::MusicError;
+use super::note::{Interval, Note, NoteName, OctaveNumber};
+
+/// 和弦性质 Chord quality, i.e. the interval formula stacked on top of the root
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum ChordQuality {
+    // 三和弦 Triads
+    Major, Minor, Diminished, Augmented,
+    // 挂留和弦 Suspended chords
+    Suspended2, Suspended4,
+    // 六和弦 Sixth chords
+    Sixth, MinorSixth,
+    // 七和弦 Seventh chords
+    Dominant7, Major7, Minor7, MinorMajor7, HalfDiminished7, Diminished7,
+    // 延伸和弦 Extended chords
+    Add9, Dominant9, Major9, Minor9, Dominant11, Minor11, Dominant13, Major13, Minor13,
+    // 变化和弦 Altered dominants
+    Dominant7Flat5, Dominant7Sharp5, Dominant7Flat9, Dominant7Sharp9, Dominant7Sharp11, Dominant7Flat13,
+}
+
+impl ChordQuality {
+    pub const ALL : [ChordQuality; 29] = [
+        ChordQuality::Major, ChordQuality::Minor, ChordQuality::Diminished, ChordQuality::Augmented,
+        ChordQuality::Suspended2, ChordQuality::Suspended4,
+        ChordQuality::Sixth, ChordQuality::MinorSixth,
+        ChordQuality::Dominant7, ChordQuality::Major7, ChordQuality::Minor7, ChordQuality::MinorMajor7,
+        ChordQuality::HalfDiminished7, ChordQuality::Diminished7,
+        ChordQuality::Add9, ChordQuality::Dominant9, ChordQuality::Major9, ChordQuality::Minor9,
+        ChordQuality::Dominant11, ChordQuality::Minor11,
+        ChordQuality::Dominant13, ChordQuality::Major13, ChordQuality::Minor13,
+        ChordQuality::Dominant7Flat5, ChordQuality::Dominant7Sharp5, ChordQuality::Dominant7Flat9,
+        ChordQuality::Dominant7Sharp9, ChordQuality::Dominant7Sharp11, ChordQuality::Dominant7Flat13,
+    ];
+
+    /// Intervals above the root, the root itself (unison) included and listed first.
+    pub const fn intervals(&self) -> &'static [Interval] {
+        use Interval as I;
+        match self {
+            ChordQuality::Major => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH],
+            ChordQuality::Minor => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH],
+            ChordQuality::Diminished => &[I::UNISON, I::MINOR_THIRD, I::DIMINISHED_FIFTH],
+            ChordQuality::Augmented => &[I::UNISON, I::MAJOR_THIRD, I::AUGMENTED_FIFTH],
+            ChordQuality::Suspended2 => &[I::UNISON, I::MAJOR_SECOND, I::PERFECT_FIFTH],
+            ChordQuality::Suspended4 => &[I::UNISON, I::PERFECT_FOURTH, I::PERFECT_FIFTH],
+            ChordQuality::Sixth => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SIXTH],
+            ChordQuality::MinorSixth => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SIXTH],
+            ChordQuality::Dominant7 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH],
+            ChordQuality::Major7 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SEVENTH],
+            ChordQuality::Minor7 => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH],
+            ChordQuality::MinorMajor7 => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SEVENTH],
+            ChordQuality::HalfDiminished7 => &[I::UNISON, I::MINOR_THIRD, I::DIMINISHED_FIFTH, I::MINOR_SEVENTH],
+            ChordQuality::Diminished7 => &[I::UNISON, I::MINOR_THIRD, I::DIMINISHED_FIFTH, I::DIMINISHED_SEVENTH],
+            ChordQuality::Add9 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_NINTH],
+            ChordQuality::Dominant9 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH],
+            ChordQuality::Major9 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SEVENTH, I::MAJOR_NINTH],
+            ChordQuality::Minor9 => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH],
+            ChordQuality::Dominant11 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH, I::PERFECT_ELEVENTH],
+            ChordQuality::Minor11 => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH, I::PERFECT_ELEVENTH],
+            ChordQuality::Dominant13 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH, I::MAJOR_THIRTEENTH],
+            ChordQuality::Major13 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MAJOR_SEVENTH, I::MAJOR_NINTH, I::MAJOR_THIRTEENTH],
+            ChordQuality::Minor13 => &[I::UNISON, I::MINOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MAJOR_NINTH, I::MAJOR_THIRTEENTH],
+            ChordQuality::Dominant7Flat5 => &[I::UNISON, I::MAJOR_THIRD, I::DIMINISHED_FIFTH, I::MINOR_SEVENTH],
+            ChordQuality::Dominant7Sharp5 => &[I::UNISON, I::MAJOR_THIRD, I::AUGMENTED_FIFTH, I::MINOR_SEVENTH],
+            ChordQuality::Dominant7Flat9 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MINOR_NINTH],
+            ChordQuality::Dominant7Sharp9 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::AUGMENTED_NINTH],
+            ChordQuality::Dominant7Sharp11 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::AUGMENTED_ELEVENTH],
+            ChordQuality::Dominant7Flat13 => &[I::UNISON, I::MAJOR_THIRD, I::PERFECT_FIFTH, I::MINOR_SEVENTH, I::MINOR_THIRTEENTH],
+        }
+    }
+
+    /// The suffix written after the root in a chord symbol, e.g. "m7b5" in "Bm7b5".
+    pub const fn symbol(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Suspended2 => "sus2",
+            ChordQuality::Suspended4 => "sus4",
+            ChordQuality::Sixth => "6",
+            ChordQuality::MinorSixth => "m6",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::MinorMajor7 => "mMaj7",
+            ChordQuality::HalfDiminished7 => "m7b5",
+            ChordQuality::Diminished7 => "dim7",
+            ChordQuality::Add9 => "add9",
+            ChordQuality::Dominant9 => "9",
+            ChordQuality::Major9 => "maj9",
+            ChordQuality::Minor9 => "m9",
+            ChordQuality::Dominant11 => "11",
+            ChordQuality::Minor11 => "m11",
+            ChordQuality::Dominant13 => "13",
+            ChordQuality::Major13 => "maj13",
+            ChordQuality::Minor13 => "m13",
+            ChordQuality::Dominant7Flat5 => "7b5",
+            ChordQuality::Dominant7Sharp5 => "7#5",
+            ChordQuality::Dominant7Flat9 => "7b9",
+            ChordQuality::Dominant7Sharp9 => "7#9",
+            ChordQuality::Dominant7Sharp11 => "7#11",
+            ChordQuality::Dominant7Flat13 => "7b13",
+        }
+    }
+}
+
+/// 和弦 Chord
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Chord {
+    root : NoteName,
+    quality : ChordQuality
+}
+
+impl Chord {
+    pub const fn of(root : NoteName, quality : ChordQuality) -> Chord {
+        Chord { root, quality }
+    }
+
+    pub fn root(&self) -> NoteName {
+        self.root.clone()
+    }
+
+    pub fn quality(&self) -> ChordQuality {
+        self.quality
+    }
+
+    /// 和弦内音 Chord tones, root first, in the order of the quality's formula.
+    pub fn note_names(&self) -> Vec<NoteName> {
+        self.quality.intervals().iter()
+            .map(|interval| self.root.add_interval(interval))
+            .collect()
+    }
+
+    /// Chord tones stacked upward from the root placed on `root_octave` (close position, extensions above the octave).
+    pub fn notes_on_octave(&self, root_octave : OctaveNumber) -> Result<Vec<Note>, MusicError> {
+        let root_note = self.root.clone().on_octave(root_octave);
+        self.quality.intervals().iter()
+            .map(|interval| root_note.add_interval(interval))
+            .collect()
+    }
+
+    pub fn string_representation(self) -> String {
+        self.root.string_representation() + self.quality.symbol()
+    }
+}
+
+/// 和弦识别结果 A chord whose tones are exactly the notes asked about, possibly without its fifth.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ChordMatch {
+    chord : Chord,
+    /// The lowest sounding note, spelled as the chord spells it.
+    bass : NoteName,
+    /// 转位 0 for root position, 1 for the third in the bass, 2 for the fifth, 3 for the seventh (or sixth).
+    /// None when the bass is an extension or the suspended tone of a sus chord, which are only written as slash chords.
+    inversion : Option<u8>,
+    is_fifth_omitted : bool,
+}
+
+impl ChordMatch {
+    pub fn chord(&self) -> &Chord {
+        &self.chord
+    }
+
+    pub fn bass(&self) -> NoteName {
+        self.bass.clone()
+    }
+
+    pub fn inversion(&self) -> Option<u8> {
+        self.inversion
+    }
+
+    pub fn is_fifth_omitted(&self) -> bool {
+        self.is_fifth_omitted
+    }
+
+    /// Chord symbol with the bass after a slash when it is not the root, e.g. "C/E".
+    pub fn string_representation(&self) -> String {
+        let chord_str = self.chord.clone().string_representation();
+        if self.inversion == Some(0) {
+            chord_str
+        } else {
+            chord_str + "/" + &self.bass.clone().string_representation()
+        }
+    }
+}
+
+/// 和弦识别 Every chord whose tones are exactly the pitch classes of `notes`, the lowest note taken as the bass.
+/// Best fit first: complete chords before ones missing their fifth, root position before inversions and slash chords,
+/// then fewer tones and the order of `ChordQuality::ALL`. E.g. A C E G gives Am7 before C6/A.
+pub fn identify_chords(notes : &[Note]) -> Vec<ChordMatch> {
+    let Some(bass_note) = notes.iter().min_by_key(|note| note.minus_note(&notes[0]).semitone_diff()) else {
+        return Vec::new();
+    };
+    let mut pitch_classes : Vec<u8> = notes.iter().map(|note| note.note_name().integer_notation()).collect();
+    pitch_classes.sort();
+    pitch_classes.dedup();
+
+    let mut matches = Vec::new();
+    for note in notes {
+        let root = note.note_name();
+        // Each played pitch class is tried once as the root, spelled as first played
+        if notes.iter().find(|other| other.note_name() == root).is_some_and(|first| !std::ptr::eq(first, note)) {
+            continue;
+        }
+
+        for quality in ChordQuality::ALL {
+            let chord = Chord::of(root.clone(), quality);
+            let chord_tones = chord.note_names();
+            let mut chord_pitch_classes : Vec<u8> = chord_tones.iter().map(NoteName::integer_notation).collect();
+            chord_pitch_classes.sort();
+
+            let is_fifth_omitted = if chord_pitch_classes == pitch_classes {
+                false
+            } else if quality.intervals().contains(&Interval::PERFECT_FIFTH) && quality.intervals().len() > 3 {
+                // Dropping the fifth is common in seventh and extended chords, not in triads
+                let fifth = root.add_interval(&Interval::PERFECT_FIFTH).integer_notation();
+                chord_pitch_classes.retain(|pitch_class| *pitch_class != fifth);
+                if chord_pitch_classes != pitch_classes {
+                    continue;
+                }
+                true
+            } else {
+                continue;
+            };
+
+            let bass_position = chord_tones.iter().position(|tone| *tone == bass_note.note_name()).unwrap_or(0);
+            let bass_interval = &quality.intervals()[bass_position];
+            let inversion = match bass_interval.number() {
+                1 => Some(0),
+                3 => Some(1),
+                5 => Some(2),
+                6 | 7 => Some(3),
+                _ => None,
+            };
+            matches.push(ChordMatch { bass: chord_tones[bass_position].clone(), chord, inversion, is_fifth_omitted });
+        }
+    }
+
+    // Stable sort keeps the order of ChordQuality::ALL among equal fits
+    matches.sort_by_key(|chord_match| (
+        chord_match.is_fifth_omitted,
+        chord_match.inversion != Some(0),
+        chord_match.inversion.is_none(),
+        chord_match.chord.quality.intervals().len(),
+    ));
+    matches
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn spelled(chord : Chord) -> Vec<String> {
+        chord.note_names().into_iter().map(NoteName::string_representation).collect()
+    }
+
+    #[test]
+    fn major_c() {
+        assert_eq!(vec!["C", "E", "G"], spelled(Chord::of(C.natural(), ChordQuality::Major)));
+    }
+
+    #[test]
+    fn half_diminished_b() {
+        assert_eq!(vec!["B", "D", "F", "A"], spelled(Chord::of(B.natural(), ChordQuality::HalfDiminished7)));
+    }
+
+    #[test]
+    fn dominant_7_b_flat_spelled_with_flats() {
+        assert_eq!(vec!["Bb", "D", "F", "Ab"], spelled(Chord::of(B.flat(), ChordQuality::Dominant7)));
+    }
+
+    #[test]
+    fn diminished_7_c_spelled_with_double_flat() {
+        assert_eq!(vec!["C", "Eb", "Gb", "Bbb"], spelled(Chord::of(C.natural(), ChordQuality::Diminished7)));
+    }
+
+    #[test]
+    fn dominant_13_g_on_2() {
+        assert_eq!(Ok(vec![
+            G.natural().on_octave(2),
+            B.natural().on_octave(2),
+            D.natural().on_octave(3),
+            F.natural().on_octave(3),
+            A.natural().on_octave(3),
+            E.natural().on_octave(4),
+        ]), Chord::of(G.natural(), ChordQuality::Dominant13).notes_on_octave(2));
+    }
+
+    fn identified(notes : &[&str]) -> Vec<String> {
+        let notes : Vec<Note> = notes.iter().map(|note| note.parse().unwrap()).collect();
+        identify_chords(&notes).iter().map(ChordMatch::string_representation).collect()
+    }
+
+    #[test]
+    fn identify_first_inversion() {
+        assert_eq!(vec!["C/E"], identified(&["E3", "G3", "C4", "E4"]));
+    }
+
+    #[test]
+    fn identify_ambiguous_am7_c6() {
+        assert_eq!(vec!["Am7", "C6/A"], identified(&["A2", "E3", "G3", "C4", "E4"]));
+        assert_eq!(vec!["C6", "Am7/C"], identified(&["C3", "A3", "E4", "G4"]));
+    }
+
+    #[test]
+    fn identify_without_fifth() {
+        let matches = identified(&["G2", "B2", "F3"]);
+        assert_eq!("G7", matches[0]);
+    }
+
+    #[test]
+    fn identify_inversion_numbers() {
+        let notes : Vec<Note> = ["F3", "G3", "B3", "D4"].iter().map(|note| note.parse().unwrap()).collect();
+        let best = &identify_chords(&notes)[0];
+        assert_eq!("G7/F", best.string_representation());
+        assert_eq!(Some(3), best.inversion());
+        assert!(identify_chords(&[]).is_empty());
+    }
+
+    #[test]
+    fn identify_sus4_over_its_fourth() {
+        let notes : Vec<Note> = ["F3", "C4", "G4"].iter().map(|note| note.parse().unwrap()).collect();
+        let csus4 = identify_chords(&notes).into_iter()
+            .find(|chord_match| chord_match.chord().quality() == ChordQuality::Suspended4)
+            .unwrap();
+        assert_eq!("Csus4/F", csus4.string_representation());
+        assert_eq!(None, csus4.inversion());
+    }
+
+    #[test]
+    fn symbol() {
+        assert_eq!("F#m7", Chord::of(F.sharp(), ChordQuality::Minor7).string_representation());
+        assert_eq!("C", Chord::of(C.natural(), ChordQuality::Major).string_representation());
+    }
+}
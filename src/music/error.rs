@@ -0,0 +1,66 @@
+use std::fmt;
+
+use super::parse::ParseMusicError;
+
+/// 乐理错误 Errors of building or transforming notes, intervals and scales
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum MusicError {
+    /// Integer notation only goes from 0 (C) to 11 (B).
+    IntegerNotationOutOfRange(u8),
+    /// The resulting note would fall below octave 0.
+    OctaveUnderflow,
+    /// The resulting note would fall above octave 255.
+    OctaveOverflow,
+    /// The resulting note would need more sharps or flats than an `Accidental` holds.
+    AccidentalOverflow,
+    /// Semitone steps of a scale must sum up to an octave.
+    InvalidStepSum { sum : u32, steps : Vec<u8> },
+    /// Diatonic scales only take seven whole or half steps.
+    NotDiatonicSteps(Vec<u8>),
+    /// Spelling one letter per degree takes seven steps.
+    NotHeptatonicSteps(Vec<u8>),
+    EmptyFormula,
+    /// The first interval of a scale formula must be the unison, i.e. the root itself.
+    FormulaNotFromRoot,
+    /// Intervals of a scale formula must go strictly upward.
+    FormulaNotAscending,
+    /// Intervals of a scale formula must stay within an octave.
+    FormulaBeyondOctave,
+    Parse(ParseMusicError),
+}
+
+impl fmt::Display for MusicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MusicError::IntegerNotationOutOfRange(integer_notation) => write!(f, "integer notation {integer_notation} is above 11"),
+            MusicError::OctaveUnderflow => write!(f, "octave below zero is not considered"),
+            MusicError::OctaveOverflow => write!(f, "octave above {} is not considered", u8::MAX),
+            MusicError::AccidentalOverflow => write!(f, "more than {} sharps or flats are not considered", i8::MAX),
+            MusicError::InvalidStepSum { sum, steps } => write!(f, "semitone steps {steps:?} sum up to {sum} instead of 12"),
+            MusicError::NotDiatonicSteps(steps) => write!(f, "semitone steps {steps:?} are not seven whole or half steps"),
+            MusicError::NotHeptatonicSteps(steps) => write!(f, "semitone steps {steps:?} are not seven steps to spell one letter per degree"),
+            MusicError::EmptyFormula => write!(f, "scale formula has no interval"),
+            MusicError::FormulaNotFromRoot => write!(f, "scale formula does not start from the root"),
+            MusicError::FormulaNotAscending => write!(f, "scale formula is not strictly ascending"),
+            MusicError::FormulaBeyondOctave => write!(f, "scale formula goes beyond an octave"),
+            MusicError::Parse(parse_error) => write!(f, "{parse_error}"),
+        }
+    }
+}
+
+impl std::error::Error for MusicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MusicError::Parse(parse_error) => Some(parse_error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseMusicError> for MusicError {
+    fn from(parse_error : ParseMusicError) -> Self {
+        MusicError::Parse(parse_error)
+    }
+}
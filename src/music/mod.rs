@@ -1,3 +1,6 @@
 pub mod note;
 pub mod scale;
-// pub mod chord;
\ No newline at end of file
+pub mod scale_catalog;
+pub mod chord;
+pub mod parse;
+pub mod error;
\ No newline at end of file
@@ -1,5 +1,7 @@
 use strum_macros::IntoStaticStr;
 
+use super::error::MusicError;
+
 /// 自然音
 #[derive(IntoStaticStr)]
 #[derive(Clone)]
@@ -9,32 +11,65 @@ pub enum NaturalNoteName {
     C, D, E, F, G, A, B
 }
 
-#[derive(Clone)]
-#[derive(PartialEq)]
-#[derive(Debug)]
-pub enum Accidental {
-    Sharp, Flat
-}
+/// 变音记号 Accidental, counted in semitones: 1 for a sharp, -1 for a flat, 2 for a double sharp, -2 for a double flat...
+pub type Accidental = i8;
 
 /// 音名
 #[derive(Clone)]
 #[derive(Debug)]
 pub struct NoteName {
     natural_note_name : NaturalNoteName,
-    accidental : Option<Accidental>
+    accidental : Accidental
 }
 
 impl NaturalNoteName {
+    pub const fn of_index(index : u8) -> NaturalNoteName {
+        match index % 7 {
+            0 => NaturalNoteName::C,
+            1 => NaturalNoteName::D,
+            2 => NaturalNoteName::E,
+            3 => NaturalNoteName::F,
+            4 => NaturalNoteName::G,
+            5 => NaturalNoteName::A,
+            _ => NaturalNoteName::B,
+        }
+    }
+
+    /// Position of the letter counting from C, which is 0.
+    pub const fn index(&self) -> u8 {
+        match self {
+            NaturalNoteName::C => 0,
+            NaturalNoteName::D => 1,
+            NaturalNoteName::E => 2,
+            NaturalNoteName::F => 3,
+            NaturalNoteName::G => 4,
+            NaturalNoteName::A => 5,
+            NaturalNoteName::B => 6,
+        }
+    }
+
     pub const fn natural(self) -> NoteName {
-        NoteName { natural_note_name: self, accidental: None }
+        NoteName { natural_note_name: self, accidental: 0 }
     }
 
     pub const fn sharp(self) -> NoteName {
-        NoteName { natural_note_name: self, accidental: Some(Accidental::Sharp) }
+        NoteName { natural_note_name: self, accidental: 1 }
     }
 
     pub const fn flat(self) -> NoteName {
-        NoteName { natural_note_name: self, accidental: Some(Accidental::Flat) }
+        NoteName { natural_note_name: self, accidental: -1 }
+    }
+
+    pub const fn double_sharp(self) -> NoteName {
+        NoteName { natural_note_name: self, accidental: 2 }
+    }
+
+    pub const fn double_flat(self) -> NoteName {
+        NoteName { natural_note_name: self, accidental: -2 }
+    }
+
+    pub const fn with_accidental(self, accidental : Accidental) -> NoteName {
+        NoteName { natural_note_name: self, accidental }
     }
 }
 
@@ -46,28 +81,79 @@ impl PartialEq for NoteName {
 }
 
 impl NoteName {
-    pub const fn of_integer_notation_as_sharp(integer_notation : u8) -> Self {
-        let natural_note_name = match integer_notation {
+    pub const fn of_integer_notation_as_sharp(integer_notation : u8) -> Result<Self, MusicError> {
+        match integer_notation {
+            0..=11 => Ok(Self::of_pitch_class_as_sharp(integer_notation)),
+            12.. => Err(MusicError::IntegerNotationOutOfRange(integer_notation)),
+        }
+    }
+
+    pub const fn of_integer_notation_as_flat(integer_notation : u8) -> Result<Self, MusicError> {
+        match integer_notation {
+            0..=11 => Ok(Self::of_pitch_class_as_flat(integer_notation)),
+            12.. => Err(MusicError::IntegerNotationOutOfRange(integer_notation)),
+        }
+    }
+
+    /// Integer notations above 11 are taken modulo 12.
+    pub(crate) const fn of_pitch_class_as_sharp(integer_notation : u8) -> Self {
+        let natural_note_name = match integer_notation % 12 {
             0..=1 => NaturalNoteName::C,
             2..=3 => NaturalNoteName::D,
             4 => NaturalNoteName::E,
             5..=6 => NaturalNoteName::F,
             7..=8 => NaturalNoteName::G,
             9..=10 => NaturalNoteName::A,
-            11 => NaturalNoteName::B,
-            12.. => panic!("Integer notation can not be above 11")
+            _ => NaturalNoteName::B,
         };
 
-        let accdental = match integer_notation {
-            1 | 3 | 6 | 8 | 10 => Some(Accidental::Sharp),
-            _ => None
+        let accdental = match integer_notation % 12 {
+            1 | 3 | 6 | 8 | 10 => 1,
+            _ => 0
         };
 
-        NoteName { natural_note_name: natural_note_name, accidental: accdental }
+        NoteName { natural_note_name, accidental: accdental }
+    }
+
+    /// Integer notations above 11 are taken modulo 12.
+    pub(crate) const fn of_pitch_class_as_flat(integer_notation : u8) -> Self {
+        let sharp_spelled = Self::of_pitch_class_as_sharp(integer_notation);
+        match sharp_spelled.accidental {
+            1 => Self::of_pitch_class_as_sharp(integer_notation + 1).natural_note_name.flat(),
+            _ => sharp_spelled,
+        }
+    }
+
+    /// Spells the note name an interval above (or below, if descending) by the interval's degree.
+    /// Accidentals beyond what an `Accidental` holds are clamped.
+    pub fn add_interval(&self, interval : &Interval) -> NoteName {
+        let (res_natural_note_name, res_accidental) = self.spell_interval(interval);
+        res_natural_note_name.with_accidental(res_accidental.clamp(Accidental::MIN as i32, Accidental::MAX as i32) as Accidental)
+    }
+
+    /// The letter an interval away and the accidental it needs, counted in i32 since both may go past an i8
+    fn spell_interval(&self, interval : &Interval) -> (NaturalNoteName, i32) {
+        let letter_steps = self.natural_note_name.index() as i32 + interval.degree_diff as i32;
+        let res_natural_note_name = NaturalNoteName::of_index(letter_steps.rem_euclid(7) as u8);
+        let natural_semitones = letter_steps.div_euclid(7) * 12 + res_natural_note_name.clone().natural().directional_integer_notation();
+        let res_semitones = self.directional_integer_notation() + interval.semitone_diff as i32;
+        (res_natural_note_name, res_semitones - natural_semitones)
+    }
+
+    /// The simple interval from `root` up to this note name, spelled by their letters: from C, Eb is a minor third and D# an augmented second.
+    pub fn interval_above(&self, root : &NoteName) -> Interval {
+        let degree_diff = (self.natural_note_name.index() as i8 - root.natural_note_name.index() as i8).rem_euclid(7);
+        let semitone_diff = (self.directional_integer_notation() - root.directional_integer_notation()).rem_euclid(12) as i8;
+        // Within half an octave of the unaltered interval, e.g. B# above C is an augmented seventh rather than a unison
+        let unaltered_semitone_diff = Interval::major_or_perfect_semitone_diff(degree_diff);
+        let semitone_diff = [semitone_diff - 12, semitone_diff, semitone_diff + 12].into_iter()
+            .min_by_key(|semitone_diff| (semitone_diff - unaltered_semitone_diff).abs())
+            .unwrap_or(semitone_diff);
+        Interval { degree_diff, semitone_diff }
     }
 
     /// Over 11 means next octave, below 0 means last octave.
-    pub const fn directional_integer_notation(&self) -> i8 {
+    pub const fn directional_integer_notation(&self) -> i32 {
         let natural_part = match self.natural_note_name {
                 NaturalNoteName::C => 0,
                 NaturalNoteName::D => 2,
@@ -78,38 +164,37 @@ impl NoteName {
                 NaturalNoteName::B => 11,
         };
 
-        let accidental_part = match &self.accidental {
-            Some(acc) => match acc {
-                Accidental::Sharp => 1,
-                Accidental::Flat => -1,
-            },
-            None => 0,
-        };
-
-        natural_part + accidental_part
+        natural_part + self.accidental_cnt() as i32
     }
 
     pub const fn integer_notation(&self) -> u8 {
-        (self.directional_integer_notation() % 12) as u8
+        self.directional_integer_notation().rem_euclid(12) as u8
+    }
+
+    /// Sharps counted positive, flats negative.
+    pub const fn accidental_cnt(&self) -> Accidental {
+        self.accidental
+    }
+
+    pub fn natural_note_name(&self) -> NaturalNoteName {
+        self.natural_note_name.clone()
     }
 
     pub fn string_representation(self) -> String {
-        let accidental_str = match self.accidental {
-            Some(acc) => match acc {
-                Accidental::Sharp => "#",
-                Accidental::Flat => "b",
-            },
-            None => "",
+        let accidental_str = if self.accidental >= 0 {
+            "#".repeat(self.accidental as usize)
+        } else {
+            "b".repeat(self.accidental.unsigned_abs() as usize)
         };
 
         let note_natural_part_name_string : &'static str = self.natural_note_name.into();
-        String::from(note_natural_part_name_string) + accidental_str
+        String::from(note_natural_part_name_string) + &accidental_str
     }
 }
 
 
 
-type OctaveNumber = u8;
+pub type OctaveNumber = u8;
 #[derive(Clone)]
 #[derive(Debug)]
 pub struct Note {
@@ -119,21 +204,52 @@ pub struct Note {
 
 impl NoteName {
     pub const fn on_octave(self, octave : OctaveNumber) -> Note {
-        Note { name: self, octave : octave }
+        Note { name: self, octave }
     }
 }
 
 impl PartialEq for Note {
     fn eq(&self, other: &Self) -> bool {
-        self.minus_note(other) == Interval::UNISON
+        self.minus_note(other).semitone_diff() == 0
     }
 }
 
 // C₄
-pub const MIDDLE_C : Note = Note{name : NoteName{natural_note_name: NaturalNoteName::C, accidental: None}, octave : 4};
+pub const MIDDLE_C : Note = Note{name : NoteName{natural_note_name: NaturalNoteName::C, accidental: 0}, octave : 4};
+
+/// 音程性质 Interval quality
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum IntervalQuality {
+    /// 减音程, the count tells doubly diminished and beyond
+    Diminished(u8),
+    Minor,
+    Perfect,
+    Major,
+    /// 增音程, the count tells doubly augmented and beyond
+    Augmented(u8),
+}
 
+impl IntervalQuality {
+    pub fn string_representation(self) -> String {
+        match self {
+            IntervalQuality::Diminished(cnt) => "d".repeat(cnt as usize),
+            IntervalQuality::Minor => String::from("m"),
+            IntervalQuality::Perfect => String::from("P"),
+            IntervalQuality::Major => String::from("M"),
+            IntervalQuality::Augmented(cnt) => "A".repeat(cnt as usize),
+        }
+    }
+}
+
+/// 音程 Interval, spelled: it knows both how many letters and how many semitones it spans.
+#[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Debug)]
 pub struct Interval {
+    /// Letter steps between the two notes, one less than the interval number: 0 for a unison, 2 for a third, 7 for an octave.
+    degree_diff : i8,
     /// When two notes doing operation: a minus b returns an Interval, in this Interval:
     /// if semitone_diff > 0, it means a apears after b
     /// if semitone_diff < 0, it means a apears before b
@@ -141,27 +257,161 @@ pub struct Interval {
 }
 
 impl Interval {
-    pub const UNISON : Interval = Interval{semitone_diff: 0};
-    pub const MINOR_SECOND : Interval = Interval{semitone_diff: 1};
-    pub const MAJOR_SECOND : Interval = Interval{semitone_diff: 2};
-    pub const MINOR_THIRD : Interval = Interval{semitone_diff: 3};
-    pub const MAJOR_THIRD : Interval = Interval{semitone_diff: 4};
-    pub const PERFECT_FOURTH : Interval = Interval{semitone_diff: 5};
-    pub const TRITONE : Interval = Interval{semitone_diff: 6};
-    pub const PERFECT_FIFTH : Interval = Interval{semitone_diff: 7};
-    pub const MINOR_SIXTH : Interval = Interval{semitone_diff: 8};
-    pub const MAJOR_SIXTH : Interval = Interval{semitone_diff: 9};
-    pub const MINOR_SEVENTH : Interval = Interval{semitone_diff: 10};
-    pub const MAJOR_SEVENTH : Interval = Interval{semitone_diff: 11};
-    pub const OCTAVE : Interval = Interval{semitone_diff: 12};
+    pub const UNISON : Interval = Interval{degree_diff: 0, semitone_diff: 0};
+    pub const MINOR_SECOND : Interval = Interval{degree_diff: 1, semitone_diff: 1};
+    pub const MAJOR_SECOND : Interval = Interval{degree_diff: 1, semitone_diff: 2};
+    pub const MINOR_THIRD : Interval = Interval{degree_diff: 2, semitone_diff: 3};
+    pub const MAJOR_THIRD : Interval = Interval{degree_diff: 2, semitone_diff: 4};
+    pub const PERFECT_FOURTH : Interval = Interval{degree_diff: 3, semitone_diff: 5};
+    pub const TRITONE : Interval = Interval::AUGMENTED_FOURTH;
+    pub const PERFECT_FIFTH : Interval = Interval{degree_diff: 4, semitone_diff: 7};
+    pub const MINOR_SIXTH : Interval = Interval{degree_diff: 5, semitone_diff: 8};
+    pub const MAJOR_SIXTH : Interval = Interval{degree_diff: 5, semitone_diff: 9};
+    pub const MINOR_SEVENTH : Interval = Interval{degree_diff: 6, semitone_diff: 10};
+    pub const MAJOR_SEVENTH : Interval = Interval{degree_diff: 6, semitone_diff: 11};
+    pub const OCTAVE : Interval = Interval{degree_diff: 7, semitone_diff: 12};
+
+    // 变化音程 Altered intervals, enharmonic to the ones above
+    pub const AUGMENTED_SECOND : Interval = Interval{degree_diff: 1, semitone_diff: 3};
+    pub const AUGMENTED_FOURTH : Interval = Interval{degree_diff: 3, semitone_diff: 6};
+    pub const DIMINISHED_FIFTH : Interval = Interval{degree_diff: 4, semitone_diff: 6};
+    pub const AUGMENTED_FIFTH : Interval = Interval{degree_diff: 4, semitone_diff: 8};
+    pub const DIMINISHED_SEVENTH : Interval = Interval{degree_diff: 6, semitone_diff: 9};
+
+    // 复音程 Compound intervals, used by chord extensions
+    pub const MINOR_NINTH : Interval = Interval{degree_diff: 8, semitone_diff: 13};
+    pub const MAJOR_NINTH : Interval = Interval{degree_diff: 8, semitone_diff: 14};
+    pub const AUGMENTED_NINTH : Interval = Interval{degree_diff: 8, semitone_diff: 15};
+    pub const PERFECT_ELEVENTH : Interval = Interval{degree_diff: 10, semitone_diff: 17};
+    pub const AUGMENTED_ELEVENTH : Interval = Interval{degree_diff: 10, semitone_diff: 18};
+    pub const MINOR_THIRTEENTH : Interval = Interval{degree_diff: 12, semitone_diff: 20};
+    pub const MAJOR_THIRTEENTH : Interval = Interval{degree_diff: 12, semitone_diff: 21};
+
+    /// The widest interval number built from a degree or quality, three octaves
+    pub const MAX_NUMBER : u8 = 22;
+
+    pub const fn of_degree_and_semitone_diff(degree_diff : i8, semitone_diff : i8) -> Interval {
+        Interval { degree_diff, semitone_diff }
+    }
+
+    /// 级数记法 Scale degree notation relative to the major scale: `of_degree(3, -1)` is "b3", `of_degree(11, 1)` is "#11".
+    /// None for the number 0 or above `MAX_NUMBER`.
+    pub const fn of_degree(number : u8, accidental : Accidental) -> Option<Interval> {
+        if number == 0 || number > Self::MAX_NUMBER {
+            return None;
+        }
+        let degree_diff = number as i8 - 1;
+        match Self::major_or_perfect_semitone_diff(degree_diff).checked_add(accidental) {
+            Some(semitone_diff) => Some(Interval { degree_diff, semitone_diff }),
+            None => None,
+        }
+    }
 
+    /// Picks the usual spelling of the semitone distance: m2, M2, m3, M3, P4, A4, P5, m6, M6, m7, M7, plus octaves.
     pub fn of_semitone_diff(semitone_diff : i8) -> Interval {
-        Interval { semitone_diff : semitone_diff }
+        let simple_degree_diff = match semitone_diff.rem_euclid(12) {
+            0 => 0,
+            1 | 2 => 1,
+            3 | 4 => 2,
+            5 | 6 => 3,
+            7 => 4,
+            8 | 9 => 5,
+            _ => 6,
+        };
+        Interval { degree_diff: semitone_diff.div_euclid(12) * 7 + simple_degree_diff, semitone_diff }
+    }
+
+    /// Builds e.g. a minor third from `(IntervalQuality::Minor, 3)`. None if the number can not carry the quality, like a perfect third,
+    /// or is 0 or above `MAX_NUMBER`.
+    pub fn of_quality(quality : IntervalQuality, number : u8) -> Option<Interval> {
+        if number == 0 || number > Self::MAX_NUMBER {
+            return None;
+        }
+        let degree_diff = number as i8 - 1;
+        let offset = match (quality, Self::is_perfect_kind(degree_diff)) {
+            (IntervalQuality::Perfect, true) | (IntervalQuality::Major, false) => 0,
+            (IntervalQuality::Minor, false) => -1,
+            (IntervalQuality::Augmented(cnt), _) => i8::try_from(cnt).ok()?,
+            (IntervalQuality::Diminished(cnt), true) => -i8::try_from(cnt).ok()?,
+            (IntervalQuality::Diminished(cnt), false) => -i8::try_from(cnt).ok()? - 1,
+            _ => return None,
+        };
+        let semitone_diff = Self::major_or_perfect_semitone_diff(degree_diff).checked_add(offset)?;
+        Some(Interval { degree_diff, semitone_diff })
     }
 
     pub fn semitone_diff(&self) -> i8 {
         self.semitone_diff
     }
+
+    pub fn degree_diff(&self) -> i8 {
+        self.degree_diff
+    }
+
+    pub fn is_descending(&self) -> bool {
+        self.degree_diff < 0 || (self.degree_diff == 0 && self.semitone_diff < 0)
+    }
+
+    /// The same interval pointing the other way.
+    pub fn inverse_direction(&self) -> Interval {
+        Interval { degree_diff: -self.degree_diff, semitone_diff: -self.semitone_diff }
+    }
+
+    /// 度数 The interval number, direction ignored: 1 for a unison, 3 for a third, 9 for a ninth.
+    pub fn number(&self) -> u8 {
+        self.degree_diff.unsigned_abs() + 1
+    }
+
+    pub fn is_compound(&self) -> bool {
+        self.degree_diff.abs() >= 7
+    }
+
+    /// 音程性质 The quality of the interval, direction ignored.
+    pub fn quality(&self) -> IntervalQuality {
+        let ascending = if self.is_descending() { self.inverse_direction() } else { self.clone() };
+        let offset = ascending.semitone_diff - Self::major_or_perfect_semitone_diff(ascending.degree_diff);
+        match (Self::is_perfect_kind(ascending.degree_diff), offset) {
+            (_, 1..) => IntervalQuality::Augmented(offset as u8),
+            (true, 0) => IntervalQuality::Perfect,
+            (true, _) => IntervalQuality::Diminished(offset.unsigned_abs()),
+            (false, 0) => IntervalQuality::Major,
+            (false, -1) => IntervalQuality::Minor,
+            (false, _) => IntervalQuality::Diminished(offset.unsigned_abs() - 1),
+        }
+    }
+
+    /// Quality notation like "m3", "P5", "A4", "M9", prefixed by "-" when descending.
+    pub fn string_representation(&self) -> String {
+        let direction_str = if self.is_descending() { "-" } else { "" };
+        format!("{direction_str}{}{}", self.quality().string_representation(), self.number())
+    }
+
+    /// Scale degree notation relative to the major scale, like "b3", "5", "#11", prefixed by "-" when descending.
+    pub fn degree_representation(&self) -> String {
+        let ascending = if self.is_descending() { self.inverse_direction() } else { self.clone() };
+        let offset = ascending.semitone_diff - Self::major_or_perfect_semitone_diff(ascending.degree_diff);
+        let direction_str = if self.is_descending() { "-" } else { "" };
+        let accidental_str = if offset >= 0 { "#".repeat(offset as usize) } else { "b".repeat(offset.unsigned_abs() as usize) };
+        format!("{direction_str}{accidental_str}{}", self.number())
+    }
+
+    const fn is_perfect_kind(degree_diff : i8) -> bool {
+        matches!(degree_diff.rem_euclid(7), 0 | 3 | 4)
+    }
+
+    /// Semitones of the major (or perfect) interval spanning `degree_diff` letter steps upward.
+    const fn major_or_perfect_semitone_diff(degree_diff : i8) -> i8 {
+        let simple_part = match degree_diff.rem_euclid(7) {
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            3 => 5,
+            4 => 7,
+            5 => 9,
+            _ => 11,
+        };
+        degree_diff.div_euclid(7) * 12 + simple_part
+    }
 }
 
 impl Note {
@@ -176,23 +426,159 @@ impl Note {
         self.note_name().string_representation() + &octave_string
     }
 
+    /// Notes more than ten octaves apart give the widest interval an `Interval` holds.
     pub fn minus_note(&self, other_note : &Note) -> Interval {
+        let octave_diff = self.octave as i32 - other_note.octave as i32;
         let note_name_part = self.name.directional_integer_notation() - other_note.name.directional_integer_notation();
-        let octave_part = (self.octave as i8 - other_note.octave as i8) * 12;
-        
-        Interval { semitone_diff: note_name_part + octave_part }
+        let degree_part = self.name.natural_note_name.index() as i32 - other_note.name.natural_note_name.index() as i32 + octave_diff * 7;
+
+        Interval { degree_diff: saturating_i8(degree_part), semitone_diff: saturating_i8(note_name_part + octave_diff * 12) }
+    }
+
+    /// 等音变换 The same pitch written with `note_name`, e.g. A#4 respelled with Bb gives Bb4 and B3 respelled with Cb gives Cb4.
+    /// None if `note_name` is not of the same pitch class.
+    pub fn respell(&self, note_name : &NoteName) -> Option<Note> {
+        if self.name != *note_name {
+            return None;
+        }
+        let res_octave = (self.semitones_from_octave_zero() - note_name.directional_integer_notation()) / 12;
+        Some(note_name.clone().on_octave(OctaveNumber::try_from(res_octave).ok()?))
     }
 
-    pub fn add_interval(&self, interval : &Interval) -> Note {
-        let self_from_octave_zero = self.octave as i8 * 12 + self.name.directional_integer_notation();
-        let res_from_octave_zero = self_from_octave_zero + interval.semitone_diff;
+    /// 移调 Transposes by semitones only, the result spelled with sharps.
+    pub fn add_semitones(&self, semitone_diff : i8) -> Result<Note, MusicError> {
+        let res_from_octave_zero = self.semitones_from_octave_zero() + semitone_diff as i32;
         if res_from_octave_zero < 0 {
-            panic!("Octave below zero is not considered...")
+            return Err(MusicError::OctaveUnderflow);
         }
 
-        let res_int_nota = res_from_octave_zero as u8 % 12;
-        let res_octave = res_from_octave_zero as u8 / 12;
+        let res_int_nota = (res_from_octave_zero % 12) as u8;
+        let res_octave = OctaveNumber::try_from(res_from_octave_zero / 12).map_err(|_| MusicError::OctaveOverflow)?;
 
-        NoteName::of_integer_notation_as_sharp(res_int_nota).on_octave(res_octave)
+        Ok(NoteName::of_pitch_class_as_sharp(res_int_nota).on_octave(res_octave))
     }
-}
\ No newline at end of file
+
+    /// Spells the result by the interval's degree, so a minor third above E is G rather than F##,
+    /// and a major second above Bb is C rather than B#.
+    pub fn add_interval(&self, interval : &Interval) -> Result<Note, MusicError> {
+        let res_from_octave_zero = self.semitones_from_octave_zero() + interval.semitone_diff as i32;
+        let letter_from_octave_zero = self.octave as i32 * 7 + self.name.natural_note_name.index() as i32 + interval.degree_diff as i32;
+        if res_from_octave_zero < 0 || letter_from_octave_zero < 0 {
+            return Err(MusicError::OctaveUnderflow);
+        }
+
+        let res_octave = OctaveNumber::try_from(letter_from_octave_zero / 7).map_err(|_| MusicError::OctaveOverflow)?;
+        let (res_natural_note_name, res_accidental) = self.name.spell_interval(interval);
+        let res_accidental = Accidental::try_from(res_accidental).map_err(|_| MusicError::AccidentalOverflow)?;
+        Ok(res_natural_note_name.with_accidental(res_accidental).on_octave(res_octave))
+    }
+
+    /// Semitones above C0, wide enough for any octave
+    pub fn semitones_from_octave_zero(&self) -> i32 {
+        self.octave as i32 * 12 + self.name.directional_integer_notation()
+    }
+}
+
+fn saturating_i8(value : i32) -> i8 {
+    value.clamp(i8::MIN as i32, i8::MAX as i32) as i8
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::NaturalNoteName::*;
+    use super::*;
+
+    #[test]
+    fn spelled_intervals_from_notes() {
+        let c4 = C.natural().on_octave(4);
+        assert_eq!("A4", F.sharp().on_octave(4).minus_note(&c4).string_representation());
+        assert_eq!("d5", G.flat().on_octave(4).minus_note(&c4).string_representation());
+        assert_eq!("m3", E.flat().on_octave(4).minus_note(&c4).string_representation());
+        assert_eq!("A2", D.sharp().on_octave(4).minus_note(&c4).string_representation());
+        assert_eq!("P8", C.natural().on_octave(5).minus_note(&c4).string_representation());
+        assert_eq!("M13", A.natural().on_octave(5).minus_note(&c4).string_representation());
+        assert_eq!("-M3", c4.minus_note(&E.natural().on_octave(4)).string_representation());
+    }
+
+    #[test]
+    fn quality_and_number() {
+        let b3 = B.natural().on_octave(3);
+        let interval = F.natural().on_octave(4).minus_note(&b3);
+        assert_eq!(IntervalQuality::Diminished(1), interval.quality());
+        assert_eq!(5, interval.number());
+        assert_eq!("b5", interval.degree_representation());
+        assert_eq!(Some(Interval::MINOR_NINTH), Interval::of_quality(IntervalQuality::Minor, 9));
+        assert_eq!(None, Interval::of_quality(IntervalQuality::Perfect, 3));
+        assert_eq!(None, Interval::of_quality(IntervalQuality::Perfect, 128));
+        assert_eq!(None, Interval::of_quality(IntervalQuality::Augmented(200), 4));
+        assert_eq!(None, Interval::of_degree(0, 0));
+        assert_eq!(None, Interval::of_degree(200, -1));
+    }
+
+    #[test]
+    fn interval_above_root() {
+        assert_eq!("m3", E.flat().interval_above(&C.natural()).string_representation());
+        assert_eq!("A2", D.sharp().interval_above(&C.natural()).string_representation());
+        assert_eq!("A7", B.sharp().interval_above(&C.natural()).string_representation());
+        assert_eq!("P5", C.sharp().interval_above(&F.sharp()).string_representation());
+    }
+
+    #[test]
+    fn respell_across_octave() {
+        assert_eq!(Some("Cb4".to_owned()), B.natural().on_octave(3).respell(&C.flat()).map(Note::string_representation));
+        assert_eq!(Some("Bb2".to_owned()), A.sharp().on_octave(2).respell(&B.flat()).map(Note::string_representation));
+        assert_eq!(None, A.natural().on_octave(2).respell(&B.flat()));
+    }
+
+    #[test]
+    fn double_accidentals() {
+        assert_eq!("F##", F.double_sharp().string_representation());
+        assert_eq!("Bbb", B.double_flat().string_representation());
+        assert_eq!(9, B.double_flat().integer_notation());
+        assert_eq!(0, B.sharp().integer_notation());
+        assert_eq!(-2, C.double_flat().directional_integer_notation());
+        assert_eq!("F##4", D.sharp().on_octave(4).add_interval(&Interval::MAJOR_THIRD).unwrap().string_representation());
+        assert_eq!("Bbb3", C.natural().on_octave(3).add_interval(&Interval::DIMINISHED_SEVENTH).unwrap().string_representation());
+    }
+
+    #[test]
+    fn octave_underflow() {
+        assert_eq!(Err(MusicError::OctaveUnderflow), C.natural().on_octave(0).add_semitones(-1));
+        assert_eq!(Err(MusicError::OctaveUnderflow), D.natural().on_octave(0).add_interval(&Interval::MAJOR_THIRD.inverse_direction()));
+        assert_eq!("Bb", C.natural().add_interval(&Interval::MAJOR_SECOND.inverse_direction()).string_representation());
+    }
+
+    #[test]
+    fn high_octaves() {
+        let e11 = E.natural().on_octave(11);
+        assert_eq!("F11", e11.add_semitones(1).unwrap().string_representation());
+        assert_eq!("G#11", e11.add_interval(&Interval::MAJOR_THIRD).unwrap().string_representation());
+        assert_eq!(Some("Fb11".to_owned()), e11.respell(&F.flat()).map(Note::string_representation));
+        assert_eq!(Err(MusicError::OctaveOverflow), B.natural().on_octave(255).add_semitones(1));
+        assert_eq!(Err(MusicError::OctaveOverflow), B.natural().on_octave(255).add_interval(&Interval::MINOR_SECOND));
+        assert_eq!(None, B.natural().on_octave(255).respell(&C.flat()));
+        assert_eq!(i8::MAX, C.natural().on_octave(200).minus_note(&C.natural().on_octave(0)).semitone_diff());
+    }
+
+    #[test]
+    fn wide_intervals_and_accidentals() {
+        let interval = "B9".parse::<Note>().unwrap().minus_note(&"C0".parse().unwrap());
+        assert_eq!(Ok("A#10".to_owned()), "B0".parse::<Note>().unwrap().add_interval(&interval).map(Note::string_representation));
+        let many_sharps = B.with_accidental(Accidental::MAX);
+        assert_eq!(138, many_sharps.directional_integer_notation());
+        assert_eq!(Err(MusicError::AccidentalOverflow), many_sharps.clone().on_octave(4).add_interval(&Interval::AUGMENTED_SECOND));
+        assert_eq!(Accidental::MAX, many_sharps.add_interval(&Interval::AUGMENTED_SECOND).accidental_cnt());
+    }
+
+    #[test]
+    fn integer_notation_out_of_range() {
+        assert_eq!(Err(MusicError::IntegerNotationOutOfRange(12)), NoteName::of_integer_notation_as_sharp(12));
+        assert_eq!(Ok(E.flat()), NoteName::of_integer_notation_as_flat(3));
+    }
+
+    #[test]
+    fn enharmonic_notes_are_equal() {
+        assert_eq!(C.sharp().on_octave(4), D.flat().on_octave(4));
+    }
+}
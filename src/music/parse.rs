@@ -0,0 +1,244 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::note::{Interval, IntervalQuality, NaturalNoteName, Note, NoteName, OctaveNumber};
+
+/// Octaves beyond this are out of any instrument's range, and taken as a typo
+const MAX_OCTAVE : OctaveNumber = 9;
+/// Accidentals stacked beyond this are taken as a typo, for note names and interval qualities alike
+const MAX_ACCIDENTAL_CNT : usize = 4;
+
+/// 解析错误 Errors of parsing notes and intervals from text
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum ParseMusicError {
+    Empty,
+    InvalidNaturalNoteName(char),
+    /// Sharps mixed with flats, e.g. "F#b".
+    InvalidAccidental(String),
+    /// More than `MAX_ACCIDENTAL_CNT` sharps, flats, "A"s or "d"s, e.g. "F#####".
+    TooManyAccidentals(String),
+    MissingOctave(String),
+    InvalidOctave(String),
+    UnexpectedTrailing(String),
+    InvalidIntervalQuality(String),
+    InvalidIntervalDegree(String),
+    /// A quality that the degree can not carry, e.g. "P3" or "M5".
+    QualityMismatch(String),
+}
+
+impl fmt::Display for ParseMusicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseMusicError::Empty => write!(f, "empty input"),
+            ParseMusicError::InvalidNaturalNoteName(c) => write!(f, "'{c}' is not a note letter (A to G)"),
+            ParseMusicError::InvalidAccidental(s) => write!(f, "sharps mixed with flats in \"{s}\""),
+            ParseMusicError::TooManyAccidentals(s) => write!(f, "more than {MAX_ACCIDENTAL_CNT} accidentals in \"{s}\""),
+            ParseMusicError::MissingOctave(s) => write!(f, "missing octave number in \"{s}\""),
+            ParseMusicError::InvalidOctave(s) => write!(f, "invalid octave number \"{s}\""),
+            ParseMusicError::UnexpectedTrailing(s) => write!(f, "unexpected trailing text \"{s}\""),
+            ParseMusicError::InvalidIntervalQuality(s) => write!(f, "invalid interval quality in \"{s}\""),
+            ParseMusicError::InvalidIntervalDegree(s) => write!(f, "invalid interval degree in \"{s}\""),
+            ParseMusicError::QualityMismatch(s) => write!(f, "interval \"{s}\" does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for ParseMusicError {}
+
+impl TryFrom<char> for NaturalNoteName {
+    type Error = ParseMusicError;
+
+    fn try_from(letter : char) -> Result<Self, Self::Error> {
+        match letter.to_ascii_uppercase() {
+            'C' => Ok(NaturalNoteName::C),
+            'D' => Ok(NaturalNoteName::D),
+            'E' => Ok(NaturalNoteName::E),
+            'F' => Ok(NaturalNoteName::F),
+            'G' => Ok(NaturalNoteName::G),
+            'A' => Ok(NaturalNoteName::A),
+            'B' => Ok(NaturalNoteName::B),
+            _ => Err(ParseMusicError::InvalidNaturalNoteName(letter)),
+        }
+    }
+}
+
+/// Splits "C#4" into the note name "C#" and the rest "4".
+fn split_note_name(s : &str) -> Result<(NoteName, &str), ParseMusicError> {
+    let mut chars = s.chars();
+    let natural_note_name = NaturalNoteName::try_from(chars.next().ok_or(ParseMusicError::Empty)?)?;
+    let rest = chars.as_str();
+
+    let accidental_len = rest.char_indices()
+        .find(|(_, c)| !matches!(c, '#' | 'b' | 'x' | '♯' | '♭' | '𝄪' | '𝄫'))
+        .map_or(rest.len(), |(i, _)| i);
+    let (accidental_str, rest) = rest.split_at(accidental_len);
+
+    let accidentals : Vec<i8> = accidental_str.chars()
+        .map(|c| match c {
+            '#' | '♯' => 1,
+            'x' | '𝄪' => 2,
+            '𝄫' => -2,
+            _ => -1,
+        })
+        .collect();
+    if accidentals.iter().any(|a| *a > 0) && accidentals.iter().any(|a| *a < 0) {
+        return Err(ParseMusicError::InvalidAccidental(s.to_owned()));
+    }
+    // Checked on the count of semitones, so that "Fx" counts as two
+    if accidentals.iter().map(|a| a.unsigned_abs() as usize).sum::<usize>() > MAX_ACCIDENTAL_CNT {
+        return Err(ParseMusicError::TooManyAccidentals(s.to_owned()));
+    }
+    Ok((natural_note_name.with_accidental(accidentals.iter().sum()), rest))
+}
+
+/// Parses "C", "F#", "Bb".
+impl FromStr for NoteName {
+    type Err = ParseMusicError;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let (note_name, rest) = split_note_name(s.trim())?;
+        if !rest.is_empty() {
+            return Err(ParseMusicError::UnexpectedTrailing(rest.to_owned()));
+        }
+        Ok(note_name)
+    }
+}
+
+/// Parses scientific pitch notation, e.g. "C#4", "Bb2", from octave 0 to 9.
+impl FromStr for Note {
+    type Err = ParseMusicError;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (note_name, octave_str) = split_note_name(s)?;
+        if octave_str.is_empty() {
+            return Err(ParseMusicError::MissingOctave(s.to_owned()));
+        }
+        let octave = octave_str.parse::<OctaveNumber>()
+            .ok()
+            .filter(|octave| *octave <= MAX_OCTAVE)
+            .ok_or(ParseMusicError::InvalidOctave(octave_str.to_owned()))?;
+        Ok(note_name.on_octave(octave))
+    }
+}
+
+/// Parses both the quality notation ("P5", "m3", "M7", "A4", "dd7")
+/// and the scale degree notation relative to the major scale ("5", "b3", "#11", "b9"),
+/// either prefixed by "-" for the descending interval, as `Interval::string_representation` writes it.
+impl FromStr for Interval {
+    type Err = ParseMusicError;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseMusicError::Empty);
+        }
+        match s.strip_prefix('-') {
+            Some(ascending) => parse_ascending_interval(ascending, s).map(|interval| interval.inverse_direction()),
+            None => parse_ascending_interval(s, s),
+        }
+    }
+}
+
+/// `s` without the leading "-", errors telling `full`, the text as given
+fn parse_ascending_interval(s : &str, full : &str) -> Result<Interval, ParseMusicError> {
+    let degree_start = s.find(|c : char| c.is_ascii_digit()).unwrap_or(s.len());
+    let (prefix, degree_str) = s.split_at(degree_start);
+
+    let number = degree_str.parse::<u8>()
+        .ok()
+        .filter(|number| (1..=Interval::MAX_NUMBER).contains(number))
+        .ok_or(ParseMusicError::InvalidIntervalDegree(full.to_owned()))?;
+
+    let prefix_chars : Vec<char> = prefix.chars().collect();
+    if prefix_chars.len() > MAX_ACCIDENTAL_CNT {
+        return Err(ParseMusicError::TooManyAccidentals(full.to_owned()));
+    }
+    let prefix_cnt = prefix_chars.len() as u8;
+    let of_degree = |accidental| Interval::of_degree(number, accidental).ok_or(ParseMusicError::InvalidIntervalDegree(full.to_owned()));
+    let quality = match prefix_chars.as_slice() {
+        [] => return of_degree(0),
+        ['P'] => IntervalQuality::Perfect,
+        ['M'] => IntervalQuality::Major,
+        ['m'] => IntervalQuality::Minor,
+        chars if chars.iter().all(|c| *c == 'A') => IntervalQuality::Augmented(prefix_cnt),
+        chars if chars.iter().all(|c| *c == 'd') => IntervalQuality::Diminished(prefix_cnt),
+        chars if chars.iter().all(|c| matches!(c, '#' | '♯')) => return of_degree(prefix_cnt as i8),
+        chars if chars.iter().all(|c| matches!(c, 'b' | '♭')) => return of_degree(-(prefix_cnt as i8)),
+        _ => return Err(ParseMusicError::InvalidIntervalQuality(full.to_owned())),
+    };
+    Interval::of_quality(quality, number).ok_or(ParseMusicError::QualityMismatch(full.to_owned()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    #[test]
+    fn note_names() {
+        assert_eq!(Ok(C.natural()), "C".parse());
+        assert_eq!(Ok(B.flat()), "Bb".parse());
+        assert_eq!(Ok(F.sharp()), "F♯".parse());
+        assert_eq!(Err(ParseMusicError::InvalidNaturalNoteName('H')), "H".parse::<NoteName>());
+        assert_eq!(Err(ParseMusicError::UnexpectedTrailing("4".to_owned())), "C4".parse::<NoteName>());
+        assert_eq!(Ok("F##"), "F##".parse::<NoteName>().map(NoteName::string_representation).as_deref());
+        assert_eq!(Ok("F##"), "Fx".parse::<NoteName>().map(NoteName::string_representation).as_deref());
+        assert_eq!(Err(ParseMusicError::InvalidAccidental("F#b".to_owned())), "F#b".parse::<NoteName>());
+        assert_eq!(Ok("Bbbbb"), "Bbbbb".parse::<NoteName>().map(NoteName::string_representation).as_deref());
+        assert_eq!(Err(ParseMusicError::TooManyAccidentals("Fxxx".to_owned())), "Fxxx".parse::<NoteName>());
+        let sharps = format!("C{}", "#".repeat(130));
+        assert_eq!(Err(ParseMusicError::TooManyAccidentals(sharps.clone())), sharps.parse::<NoteName>());
+    }
+
+    #[test]
+    fn notes() {
+        assert_eq!(Ok(C.sharp().on_octave(4)), "C#4".parse());
+        assert_eq!(Ok(E.flat().on_octave(2)), "Eb2".parse());
+        assert_eq!(Ok("Ebb3"), "Ebb3".parse::<Note>().map(Note::string_representation).as_deref());
+        assert_eq!(Err(ParseMusicError::MissingOctave("Eb".to_owned())), "Eb".parse::<Note>());
+        assert_eq!(Err(ParseMusicError::InvalidOctave("-1".to_owned())), "Eb-1".parse::<Note>());
+        assert_eq!(Ok(C.natural().on_octave(9)), "C9".parse());
+        assert_eq!(Err(ParseMusicError::InvalidOctave("11".to_owned())), "E11".parse::<Note>());
+        assert_eq!(Err(ParseMusicError::InvalidOctave("300".to_owned())), "E300".parse::<Note>());
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(Ok(Interval::MINOR_THIRD), "m3".parse());
+        assert_eq!(Ok(Interval::PERFECT_FIFTH), "P5".parse());
+        assert_eq!(Ok(Interval::MINOR_THIRD), "b3".parse());
+        assert_eq!(Ok(Interval::MINOR_NINTH), "b9".parse());
+        assert_eq!(Ok(Interval::AUGMENTED_ELEVENTH), "#11".parse());
+        assert_eq!(Ok(Interval::DIMINISHED_SEVENTH), "d7".parse());
+        assert_eq!(Ok(Interval::OCTAVE), "8".parse());
+        assert_eq!(Err(ParseMusicError::QualityMismatch("P3".to_owned())), "P3".parse::<Interval>());
+        assert_eq!(Err(ParseMusicError::InvalidIntervalDegree("m0".to_owned())), "m0".parse::<Interval>());
+        assert_eq!(Err(ParseMusicError::InvalidIntervalDegree("P128".to_owned())), "P128".parse::<Interval>());
+        assert_eq!(Err(ParseMusicError::InvalidIntervalDegree("b200".to_owned())), "b200".parse::<Interval>());
+        assert_eq!(Err(ParseMusicError::TooManyAccidentals("AAAAA4".to_owned())), "AAAAA4".parse::<Interval>());
+        assert_ne!(Ok(Interval::DIMINISHED_FIFTH), "A4".parse());
+        assert_eq!(Ok("dd7"), "dd7".parse::<Interval>().map(|interval| interval.string_representation()).as_deref());
+        assert_eq!(Ok(Interval::MAJOR_THIRD.inverse_direction()), "-M3".parse());
+        assert_eq!(Ok(Interval::MINOR_THIRD.inverse_direction()), "-b3".parse());
+        assert_eq!(Err(ParseMusicError::InvalidIntervalQuality("--M3".to_owned())), "--M3".parse::<Interval>());
+        assert_eq!(Err(ParseMusicError::InvalidIntervalDegree("-".to_owned())), "-".parse::<Interval>());
+    }
+
+    #[test]
+    fn interval_representations_parse_back() {
+        let qualities = [IntervalQuality::Diminished(2), IntervalQuality::Diminished(1), IntervalQuality::Minor,
+            IntervalQuality::Perfect, IntervalQuality::Major, IntervalQuality::Augmented(1), IntervalQuality::Augmented(2)];
+        for number in 1..=Interval::MAX_NUMBER {
+            for interval in qualities.iter().filter_map(|quality| Interval::of_quality(*quality, number)) {
+                for interval in [interval.inverse_direction(), interval] {
+                    assert_eq!(Ok(&interval), interval.string_representation().parse::<Interval>().as_ref(), "{}", interval.string_representation());
+                    assert_eq!(Ok(&interval), interval.degree_representation().parse::<Interval>().as_ref(), "{}", interval.degree_representation());
+                }
+            }
+        }
+    }
+}
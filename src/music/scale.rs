@@ -1,83 +1,259 @@
-use std::usize;
+use super::error::MusicError;
+use super::note::{Interval, Note, NoteName, OctaveNumber};
+use super::scale_catalog::{ScaleKind, DORIAN, MAJOR, MAJOR_PENTATONIC, MINOR, MINOR_PENTATONIC, SCALE_CATALOG};
 
-use super::note::{Interval, Note, NoteName};
+/// 拼写方式 How the notes of a scale built from steps are named
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum StepSpelling {
+    /// 七声音阶 Each letter exactly once, the i-th note named as the i-th degree, e.g. Eb F Gb Ab Bb Cb Db.
+    /// Only for seven-note scales, like the diatonic modes.
+    OneLetterPerDegree,
+    /// Each note by the usual name of its distance from the root: m2, M2, m3, M3, P4, A4, P5, m6, M6, m7, M7.
+    BySemitoneDistance,
+}
+
+/// 音阶 Scale, a root plus the intervals of every degree above it. Any number of notes.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Scale {
+    root : NoteName,
+    intervals : Vec<Interval>
+}
 
-/// 音阶 Scale
-fn scale_from_steps<const N : usize>(root : NoteName, semitone_step_arr : [u8; N]) -> [NoteName; N] {
-    let step_sum = semitone_step_arr.iter().sum::<u8>();
-    if step_sum != 12 {
-        panic!("Semitone step arr must have a sum of 12 to produce a valid scale. Getting sum {step_sum} from {semitone_step_arr:?}");
+impl Scale {
+    /// `intervals` are counted from the root: the first one must be the unison, the rest strictly ascending within an octave.
+    pub fn of_intervals(root : NoteName, intervals : Vec<Interval>) -> Result<Scale, MusicError> {
+        match intervals.as_slice() {
+            [] => return Err(MusicError::EmptyFormula),
+            [first, ..] if first.semitone_diff() != 0 || first.degree_diff() != 0 => return Err(MusicError::FormulaNotFromRoot),
+            _ => {},
+        }
+        if intervals.windows(2).any(|pair| pair[0].semitone_diff() >= pair[1].semitone_diff()) {
+            return Err(MusicError::FormulaNotAscending);
+        }
+        if intervals.iter().any(|interval| interval.semitone_diff() >= 12) {
+            return Err(MusicError::FormulaBeyondOctave);
+        }
+        Ok(Scale { root, intervals })
     }
 
-    let root_note = root.clone().on_octave(4);
-    
-    let mut scale: [NoteName; N] = core::array::from_fn(|_|{root.clone()});
-    let mut semitone_acc : u8 = 0;
-    for (i, &steps) in semitone_step_arr.iter().enumerate() {
-        let interval = Interval::of_semitone_diff(semitone_acc as i8);
-        let note = root_note.add_interval(&interval);
-        scale[i] = note.note_name();
-        semitone_acc += steps;
-    }
-    scale
-}
+    /// For formulas known to be valid, like the catalogued ones.
+    pub(super) fn of_valid_intervals(root : NoteName, intervals : &[Interval]) -> Scale {
+        Scale { root, intervals: intervals.to_vec() }
+    }
 
-/// 七声调式
-fn heptatonic_scale_from_steps(root : NoteName, semitone_step_arr : [u8; 7]) -> [NoteName; 7] {
-    scale_from_steps(root, semitone_step_arr)
-}
+    /// The steps go from each note to the next one, the last step back to the root an octave up.
+    pub fn of_steps(root : NoteName, semitone_steps : &[u8], spelling : StepSpelling) -> Result<Scale, MusicError> {
+        if semitone_steps.is_empty() {
+            return Err(MusicError::EmptyFormula);
+        }
+        let step_sum = semitone_steps.iter().map(|step| *step as u32).sum::<u32>();
+        if step_sum != 12 || semitone_steps.contains(&0) {
+            return Err(MusicError::InvalidStepSum { sum: step_sum, steps: semitone_steps.to_vec() });
+        }
+        if spelling == StepSpelling::OneLetterPerDegree && semitone_steps.len() != 7 {
+            return Err(MusicError::NotHeptatonicSteps(semitone_steps.to_vec()));
+        }
 
-/// 自然音阶
-fn diatonic_scale_from_steps(root : NoteName, semitone_step_arr : [u8; 7]) -> [NoteName; 7] {
-    let is_all_step_whole_or_half = semitone_step_arr.iter().all(|step| *step == 1 || *step == 2);
-    if ! is_all_step_whole_or_half {
-        panic!("Semitone steps must all be 1 or 2 to produce a diatonic scale. Getting {semitone_step_arr:?}");
+        let mut intervals = Vec::with_capacity(semitone_steps.len());
+        let mut semitone_acc : u8 = 0;
+        for (i, &steps) in semitone_steps.iter().enumerate() {
+            intervals.push(match spelling {
+                StepSpelling::OneLetterPerDegree => Interval::of_degree_and_semitone_diff(i as i8, semitone_acc as i8),
+                StepSpelling::BySemitoneDistance => Interval::of_semitone_diff(semitone_acc as i8),
+            });
+            semitone_acc += steps;
+        }
+        Ok(Scale { root, intervals })
+    }
+
+    /// 自然音阶 Seven steps, each a whole or a half one, spelled one letter per degree.
+    pub fn of_diatonic_steps(root : NoteName, semitone_steps : &[u8]) -> Result<Scale, MusicError> {
+        let is_all_step_whole_or_half = semitone_steps.iter().all(|step| *step == W || *step == H);
+        if semitone_steps.len() != 7 || !is_all_step_whole_or_half {
+            return Err(MusicError::NotDiatonicSteps(semitone_steps.to_vec()));
+        }
+        Self::of_steps(root, semitone_steps, StepSpelling::OneLetterPerDegree)
+    }
+
+    /// User defined scale in scale degree notation, e.g. "1 2 b3 4 5 b6 7" or "1, b3, 4, #4, 5, b7".
+    pub fn of_formula(root : NoteName, formula : &str) -> Result<Scale, MusicError> {
+        let intervals = formula
+            .split(|c : char| c.is_whitespace() || c == ',')
+            .filter(|degree| !degree.is_empty())
+            .map(str::parse::<Interval>)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::of_intervals(root, intervals)
+    }
+
+    pub fn root(&self) -> NoteName {
+        self.root.clone()
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    pub fn note_cnt(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// 级 The note on a degree counted from 1 like number notation, wrapping around past the last degree:
+    /// degree 8 of a seven-note scale is the root again.
+    pub fn degree(&self, number : usize) -> Option<NoteName> {
+        if number == 0 {
+            return None;
+        }
+        let interval = &self.intervals[(number - 1) % self.intervals.len()];
+        Some(self.root.add_interval(interval))
+    }
+
+    pub fn note_names(&self) -> Vec<NoteName> {
+        self.iter().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = NoteName> + '_ {
+        self.intervals.iter().map(|interval| self.root.add_interval(interval))
+    }
+
+    /// Whether the pitch class belongs to the scale, spelling ignored.
+    pub fn contains(&self, note_name : &NoteName) -> bool {
+        self.iter().any(|scale_note_name| scale_note_name == *note_name)
+    }
+
+    /// Number notation of the pitch class in the scale, spelling ignored.
+    pub fn degree_of(&self, note_name : &NoteName) -> Option<usize> {
+        self.iter().position(|scale_note_name| scale_note_name == *note_name).map(|i| i + 1)
+    }
+
+    /// A smaller scale made by leaving out some degrees, e.g. the major pentatonic is the major scale without 4 and 7.
+    /// The root is always kept.
+    pub fn omitting(&self, omit_number_notations : &[usize]) -> Scale {
+        let intervals = self.intervals.iter().enumerate()
+            .filter(|(i, _)| *i == 0 || !omit_number_notations.contains(&(i + 1)))
+            .map(|(_, interval)| interval.clone())
+            .collect();
+        Scale { root: self.root.clone(), intervals }
+    }
+
+    /// The notes going up from the root placed on `root_octave`.
+    pub fn notes_on_octave(&self, root_octave : OctaveNumber) -> Result<Vec<Note>, MusicError> {
+        let root_note = self.root.clone().on_octave(root_octave);
+        self.intervals.iter().map(|interval| root_note.add_interval(interval)).collect()
+    }
+
+    pub fn string_representation(&self) -> String {
+        self.iter().map(NoteName::string_representation).collect::<Vec<_>>().join(" ")
     }
-    scale_from_steps(root, semitone_step_arr)
 }
 
-const W : u8 = 2; // 全音 whole-tone
-const H : u8 = 1; // 半音 half-tone
+pub const W : u8 = 2; // 全音 whole-tone
+pub const H : u8 = 1; // 半音 half-tone
+
+fn scale_from_formula<const N : usize>(root : NoteName, formula : &[Interval; N]) -> [NoteName; N] {
+    formula.each_ref().map(|interval| root.add_interval(interval))
+}
 
 pub fn major_scale_of(root : NoteName) -> [NoteName; 7] {
-    diatonic_scale_from_steps(root, [W, W, H, W, W, W, H])
+    scale_from_formula(root, &MAJOR)
 }
 
 pub fn minor_scale_of(root : NoteName) -> [NoteName; 7] {
-    diatonic_scale_from_steps(root, [W, H, W, W, H, W, W])
+    scale_from_formula(root, &MINOR)
 }
 
 pub fn dorian_scale_of(root : NoteName) -> [NoteName; 7] {
-    diatonic_scale_from_steps(root, [W, H, W, W, W, H, W])
+    scale_from_formula(root, &DORIAN)
 }
 
 /// 五声调式
-fn pentatonic_scale_by_omitting(heptatonic : [NoteName; 7], omit_number_notation1 : usize, omit_number_notation2 : usize) -> [NoteName; 5] {
-    let mut tmp_vec = heptatonic.to_vec();
-    let mut number_notations_to_remove = vec![omit_number_notation1, omit_number_notation2];
-    number_notations_to_remove.sort();
-    for number_notation_to_remove in number_notations_to_remove.iter().rev() {
-        tmp_vec.remove(number_notation_to_remove - 1);
-    }
-
-    core::array::from_fn(|i| {
-        match tmp_vec.get(i) {
-            Some(note_name) => note_name.clone(),
-            None => panic!("No element found at index {i} in vec {tmp_vec:?}"),
-        }
-    })
-}
-
 pub fn major_pentatonic_scale_of(root : NoteName) -> [NoteName; 5] {
-    pentatonic_scale_by_omitting(major_scale_of(root), 4, 7)
+    scale_from_formula(root, &MAJOR_PENTATONIC)
 }
 
 pub fn minor_pentatonic_scale_of(root : NoteName) -> [NoteName; 5] {
-    pentatonic_scale_by_omitting(minor_scale_of(root), 2, 6)
+    scale_from_formula(root, &MINOR_PENTATONIC)
+}
+
+/// 音阶识别结果 A catalogued scale containing every note asked about.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ScaleMatch {
+    kind : &'static ScaleKind,
+    scale : Scale,
+    /// Scale notes that were not asked about; the fewer, the better the fit.
+    extra_note_cnt : usize,
+    /// Whether the scale's root is among the notes asked about.
+    is_root_played : bool,
+}
+
+impl ScaleMatch {
+    pub fn kind(&self) -> &'static ScaleKind {
+        self.kind
+    }
+
+    pub fn scale(&self) -> &Scale {
+        &self.scale
+    }
+
+    pub fn extra_note_cnt(&self) -> usize {
+        self.extra_note_cnt
+    }
+
+    pub fn is_root_played(&self) -> bool {
+        self.is_root_played
+    }
+
+    pub fn string_representation(&self) -> String {
+        self.scale.root().string_representation() + " " + self.kind.name()
+    }
+}
+
+/// 音阶识别 Every catalogued scale, on every root, that contains all the given pitch classes, best fit first:
+/// fewer notes outside the given ones, then scales rooted on a given note, then the catalog order, which lists common scales first.
+pub fn identify_scales(note_names : &[NoteName]) -> Vec<ScaleMatch> {
+    let mut pitch_classes : Vec<u8> = note_names.iter().map(NoteName::integer_notation).collect();
+    pitch_classes.sort();
+    pitch_classes.dedup();
+    if pitch_classes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for kind in SCALE_CATALOG {
+        for root_integer_notation in 0..12 {
+            let scale_pitch_classes : Vec<u8> = kind.intervals().iter()
+                .map(|interval| (root_integer_notation + interval.semitone_diff() as u8) % 12)
+                .collect();
+            if !pitch_classes.iter().all(|pitch_class| scale_pitch_classes.contains(pitch_class)) {
+                continue;
+            }
+
+            // Spell the root as played if possible
+            let root = note_names.iter()
+                .find(|note_name| note_name.integer_notation() == root_integer_notation)
+                .cloned()
+                .unwrap_or(NoteName::of_pitch_class_as_sharp(root_integer_notation));
+            matches.push(ScaleMatch {
+                kind,
+                scale: kind.of_simplest_spelling(root),
+                extra_note_cnt: scale_pitch_classes.len() - pitch_classes.len(),
+                is_root_played: pitch_classes.contains(&root_integer_notation),
+            });
+        }
+    }
+
+    // Stable sort keeps the catalog order among equal fits
+    matches.sort_by_key(|scale_match| (scale_match.extra_note_cnt, !scale_match.is_root_played));
+    matches
 }
 
 /// 音阶绝对化，即将音阶中的每个音名转换成科学表示法（国际表示法）。假设了音阶数组仅覆盖一个八度
-trait Absolutifiable<const N: usize> { fn on_octave(self, root_octave : u8) -> [Note; N]; }
+pub trait Absolutifiable<const N: usize> { fn on_octave(self, root_octave : u8) -> [Note; N]; }
 impl <const N: usize> Absolutifiable<N> for [NoteName; N] {
     fn on_octave(self, root_octave : u8) -> [Note; N] {
         let root_note_name_int = self[0].clone().integer_notation();
@@ -124,6 +300,98 @@ mod tests {
             ], minor_scale_of(C.natural()));
     }
 
+    #[test]
+    fn major_f_spelled_with_flat() {
+        assert_eq!(["F", "G", "A", "Bb", "C", "D", "E"],
+            major_scale_of(F.natural()).map(|note_name| note_name.string_representation()));
+    }
+
+    #[test]
+    fn minor_e_flat_spelled_one_letter_per_degree() {
+        assert_eq!(["Eb", "F", "Gb", "Ab", "Bb", "Cb", "Db"],
+            minor_scale_of(E.flat()).map(|note_name| note_name.string_representation()));
+    }
+
+    #[test]
+    fn major_pentatonic_d_flat_keeps_key_spelling() {
+        assert_eq!(["Db", "Eb", "F", "Ab", "Bb"],
+            major_pentatonic_scale_of(D.flat()).map(|note_name| note_name.string_representation()));
+    }
+
+    #[test]
+    fn user_defined_harmonic_minor() {
+        let scale = Scale::of_formula(D.natural(), "1 2 b3 4 5 b6 7").unwrap();
+        assert_eq!("D E F G A Bb C#", scale.string_representation());
+        assert_eq!(Some(B.flat()), scale.degree(6));
+        assert_eq!(Some(D.natural()), scale.degree(8));
+        assert_eq!(Some(7), scale.degree_of(&D.flat()));
+    }
+
+    #[test]
+    fn invalid_formulas() {
+        assert_eq!(Err(MusicError::InvalidStepSum { sum: 11, steps: vec![W, W, H, W, W, W] }),
+            Scale::of_steps(C.natural(), &[W, W, H, W, W, W], StepSpelling::BySemitoneDistance));
+        assert_eq!(Err(MusicError::NotHeptatonicSteps(vec![W, W, 3, W, 3])),
+            Scale::of_steps(C.natural(), &[W, W, 3, W, 3], StepSpelling::OneLetterPerDegree));
+        assert_eq!(Err(MusicError::NotDiatonicSteps(vec![3, H, W, W, H, 3])),
+            Scale::of_diatonic_steps(C.natural(), &[3, H, W, W, H, 3]));
+        assert_eq!(Err(MusicError::FormulaNotFromRoot), Scale::of_formula(C.natural(), "2 3 5"));
+        assert_eq!(Err(MusicError::FormulaNotAscending), Scale::of_formula(C.natural(), "1 3 2"));
+        assert_eq!(Err(MusicError::FormulaBeyondOctave), Scale::of_formula(C.natural(), "1 3 5 9"));
+        assert!(matches!(Scale::of_formula(C.natural(), "1 3 Q5"), Err(MusicError::Parse(_))));
+    }
+
+    #[test]
+    fn octatonic_from_steps() {
+        let scale = Scale::of_steps(C.natural(), &[H, W, H, W, H, W, H, W], StepSpelling::BySemitoneDistance).unwrap();
+        assert_eq!(8, scale.note_cnt());
+        assert!(scale.contains(&F.sharp()));
+        assert!(!scale.contains(&F.natural()));
+    }
+
+    #[test]
+    fn spelling_from_steps() {
+        let lydian_sharp_2 = [3, H, W, H, W, W, H];
+        assert_eq!("C D# E F# G A B",
+            Scale::of_steps(C.natural(), &lydian_sharp_2, StepSpelling::OneLetterPerDegree).unwrap().string_representation());
+        assert_eq!("C Eb E F# G A B",
+            Scale::of_steps(C.natural(), &lydian_sharp_2, StepSpelling::BySemitoneDistance).unwrap().string_representation());
+    }
+
+    #[test]
+    fn omitting_degrees() {
+        let scale = Scale::of_formula(G.natural(), "1 2 3 4 5 6 7").unwrap().omitting(&[4, 7]);
+        assert_eq!("G A B D E", scale.string_representation());
+    }
+
+    #[test]
+    fn identify_c_major_notes() {
+        let notes = [C.natural(), D.natural(), E.natural(), F.natural(), G.natural(), A.natural(), B.natural()];
+        let matches : Vec<String> = identify_scales(&notes).iter()
+            .take(7)
+            .map(ScaleMatch::string_representation)
+            .collect();
+        assert_eq!(vec![
+            "C Major", "D Dorian", "E Phrygian", "F Lydian", "G Mixolydian", "A Minor", "B Locrian",
+        ], matches);
+    }
+
+    #[test]
+    fn identify_pentatonic_before_heptatonic() {
+        let notes = [A.natural(), C.natural(), D.natural(), E.natural(), G.natural()];
+        let best = &identify_scales(&notes)[0];
+        assert_eq!(0, best.extra_note_cnt());
+        assert!(best.is_root_played());
+        assert_eq!("C Major Pentatonic", best.string_representation());
+    }
+
+    #[test]
+    fn identify_spells_by_key() {
+        let notes = [A.sharp(), D.natural(), F.natural()];
+        assert!(identify_scales(&notes).iter().any(|scale_match| scale_match.string_representation() == "Bb Major"));
+        assert!(identify_scales(&[]).is_empty());
+    }
+
     #[test]
     fn dorian_a_on_4() {
         assert_eq!([
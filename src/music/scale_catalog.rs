@@ -0,0 +1,196 @@
+use super::note::{Interval, NoteName};
+use super::scale::Scale;
+
+/// 音阶类型 A named scale formula, the intervals being counted from the root in scale degree notation.
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ScaleKind {
+    name : &'static str,
+    aliases : &'static [&'static str],
+    intervals : &'static [Interval],
+}
+
+impl ScaleKind {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    /// Intervals above the root, the root itself (unison) included and listed first.
+    pub fn intervals(&self) -> &'static [Interval] {
+        self.intervals
+    }
+
+    pub fn note_cnt(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Looks up the catalog by name or alias, ignoring case, spaces, hyphens and underscores.
+    pub fn by_name(name : &str) -> Option<&'static ScaleKind> {
+        let normalize = |s : &str| -> String {
+            s.chars()
+                .filter(|c| !matches!(c, ' ' | '-' | '_'))
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+        let name = normalize(name);
+        SCALE_CATALOG.iter().find(|kind| {
+            normalize(kind.name) == name || kind.aliases.iter().any(|alias| normalize(alias) == name)
+        })
+    }
+
+    /// The scale built on `root`, each note named after its degree.
+    pub fn of(&self, root : NoteName) -> Scale {
+        Scale::of_valid_intervals(root, self.intervals)
+    }
+
+    /// 等音选择 Between the given, the sharp and the flat spelling of the root, builds the scale on the one needing fewer accidentals,
+    /// e.g. Bb major rather than A# major.
+    pub fn of_simplest_spelling(&self, root : NoteName) -> Scale {
+        let accidental_sum = |scale : &Scale| -> u32 {
+            scale.iter().map(|note_name| note_name.accidental_cnt().unsigned_abs() as u32).sum()
+        };
+
+        let integer_notation = root.integer_notation();
+        [
+            self.of(NoteName::of_pitch_class_as_sharp(integer_notation)),
+            self.of(NoteName::of_pitch_class_as_flat(integer_notation)),
+        ]
+            .into_iter()
+            .fold(self.of(root), |best, scale| if accidental_sum(&scale) < accidental_sum(&best) { scale } else { best })
+    }
+}
+
+// Scale degree notation helpers for the formulas below
+const fn n(number : u8) -> Interval { Interval::of_degree(number, 0).unwrap() }
+const fn b(number : u8) -> Interval { Interval::of_degree(number, -1).unwrap() }
+const fn bb(number : u8) -> Interval { Interval::of_degree(number, -2).unwrap() }
+const fn s(number : u8) -> Interval { Interval::of_degree(number, 1).unwrap() }
+
+pub const MAJOR : [Interval; 7] = [n(1), n(2), n(3), n(4), n(5), n(6), n(7)];
+pub const MINOR : [Interval; 7] = [n(1), n(2), b(3), n(4), n(5), b(6), b(7)];
+pub const DORIAN : [Interval; 7] = [n(1), n(2), b(3), n(4), n(5), n(6), b(7)];
+pub const MAJOR_PENTATONIC : [Interval; 5] = [n(1), n(2), n(3), n(5), n(6)];
+pub const MINOR_PENTATONIC : [Interval; 5] = [n(1), b(3), n(4), n(5), b(7)];
+
+const fn kind(name : &'static str, aliases : &'static [&'static str], intervals : &'static [Interval]) -> ScaleKind {
+    ScaleKind { name, aliases, intervals }
+}
+
+/// 音阶目录 Every catalogued scale. Seven-note scales name each degree with its own letter.
+pub static SCALE_CATALOG : &[ScaleKind] = &[
+    // 自然音阶调式 Diatonic modes
+    kind("Major", &["Ionian"], &MAJOR),
+    kind("Dorian", &[], &DORIAN),
+    kind("Phrygian", &[], &[n(1), b(2), b(3), n(4), n(5), b(6), b(7)]),
+    kind("Lydian", &[], &[n(1), n(2), n(3), s(4), n(5), n(6), n(7)]),
+    kind("Mixolydian", &[], &[n(1), n(2), n(3), n(4), n(5), n(6), b(7)]),
+    kind("Minor", &["Aeolian", "Natural Minor"], &MINOR),
+    kind("Locrian", &[], &[n(1), b(2), b(3), n(4), b(5), b(6), b(7)]),
+    // 和声小调及其调式 Harmonic minor and its modes
+    kind("Harmonic Minor", &[], &[n(1), n(2), b(3), n(4), n(5), b(6), n(7)]),
+    kind("Locrian #6", &[], &[n(1), b(2), b(3), n(4), b(5), n(6), b(7)]),
+    kind("Ionian #5", &["Ionian Augmented"], &[n(1), n(2), n(3), n(4), s(5), n(6), n(7)]),
+    kind("Dorian #4", &["Ukrainian Dorian"], &[n(1), n(2), b(3), s(4), n(5), n(6), b(7)]),
+    kind("Phrygian Dominant", &["Spanish Gypsy"], &[n(1), b(2), n(3), n(4), n(5), b(6), b(7)]),
+    kind("Lydian #2", &[], &[n(1), s(2), n(3), s(4), n(5), n(6), n(7)]),
+    kind("Ultralocrian", &["Super Locrian bb7"], &[n(1), b(2), b(3), b(4), b(5), b(6), bb(7)]),
+    // 旋律小调及其调式 Melodic minor and its modes
+    kind("Melodic Minor", &["Jazz Minor"], &[n(1), n(2), b(3), n(4), n(5), n(6), n(7)]),
+    kind("Dorian b2", &["Phrygian #6"], &[n(1), b(2), b(3), n(4), n(5), n(6), b(7)]),
+    kind("Lydian Augmented", &[], &[n(1), n(2), n(3), s(4), s(5), n(6), n(7)]),
+    kind("Lydian Dominant", &["Overtone"], &[n(1), n(2), n(3), s(4), n(5), n(6), b(7)]),
+    kind("Mixolydian b6", &["Aeolian Dominant"], &[n(1), n(2), n(3), n(4), n(5), b(6), b(7)]),
+    kind("Locrian #2", &["Half Diminished"], &[n(1), n(2), b(3), n(4), b(5), b(6), b(7)]),
+    kind("Altered", &["Super Locrian"], &[n(1), b(2), b(3), b(4), b(5), b(6), b(7)]),
+    // 五声调式 Pentatonic scales
+    kind("Major Pentatonic", &[], &MAJOR_PENTATONIC),
+    kind("Minor Pentatonic", &[], &MINOR_PENTATONIC),
+    kind("Hirajoshi", &[], &[n(1), n(2), b(3), n(5), b(6)]),
+    kind("In-sen", &[], &[n(1), b(2), n(4), n(5), b(7)]),
+    kind("Iwato", &[], &[n(1), b(2), n(4), b(5), b(7)]),
+    // 布鲁斯 Blues
+    kind("Blues", &["Minor Blues"], &[n(1), b(3), n(4), b(5), n(5), b(7)]),
+    kind("Major Blues", &[], &[n(1), n(2), b(3), n(3), n(5), n(6)]),
+    // 对称音阶 Symmetric scales
+    kind("Whole Tone", &[], &[n(1), n(2), n(3), s(4), s(5), b(7)]),
+    kind("Diminished Half-Whole", &["Dominant Diminished"], &[n(1), b(2), s(2), n(3), s(4), n(5), n(6), b(7)]),
+    kind("Diminished Whole-Half", &["Diminished"], &[n(1), n(2), b(3), n(4), b(5), s(5), n(6), n(7)]),
+    kind("Chromatic", &[], &[n(1), b(2), n(2), b(3), n(3), n(4), s(4), n(5), b(6), n(6), b(7), n(7)]),
+    // 比波普 Bebop
+    kind("Bebop Dominant", &[], &[n(1), n(2), n(3), n(4), n(5), n(6), b(7), n(7)]),
+    kind("Bebop Major", &[], &[n(1), n(2), n(3), n(4), n(5), s(5), n(6), n(7)]),
+    kind("Bebop Dorian", &["Bebop Minor"], &[n(1), n(2), b(3), n(3), n(4), n(5), n(6), b(7)]),
+    // 其他 Others
+    kind("Hungarian Minor", &["Gypsy Minor"], &[n(1), n(2), b(3), s(4), n(5), b(6), n(7)]),
+    kind("Double Harmonic", &["Byzantine"], &[n(1), b(2), n(3), n(4), n(5), b(6), n(7)]),
+    kind("Neapolitan Minor", &[], &[n(1), b(2), b(3), n(4), n(5), b(6), n(7)]),
+    kind("Neapolitan Major", &[], &[n(1), b(2), b(3), n(4), n(5), n(6), n(7)]),
+];
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn spelled(scale : Scale) -> Vec<String> {
+        scale.iter().map(NoteName::string_representation).collect()
+    }
+
+    #[test]
+    fn lookup_by_name_and_alias() {
+        assert_eq!(Some("Minor"), ScaleKind::by_name("aeolian").map(ScaleKind::name));
+        assert_eq!(Some("Phrygian Dominant"), ScaleKind::by_name("phrygian-dominant").map(ScaleKind::name));
+        assert_eq!(None, ScaleKind::by_name("Not A Scale"));
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for kind in SCALE_CATALOG {
+            for name in std::iter::once(&kind.name()).chain(kind.aliases()) {
+                assert!(std::ptr::eq(kind, ScaleKind::by_name(name).unwrap()), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn formulas_span_less_than_an_octave() {
+        for kind in SCALE_CATALOG {
+            assert_eq!(&Interval::UNISON, &kind.intervals()[0], "{}", kind.name());
+            assert!(kind.intervals().windows(2).all(|w| w[0].semitone_diff() < w[1].semitone_diff()), "{}", kind.name());
+            assert!(kind.intervals().iter().all(|interval| interval.semitone_diff() < 12), "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn harmonic_minor_g_sharp() {
+        assert_eq!(vec!["G#", "A#", "B", "C#", "D#", "E", "F##"],
+            spelled(ScaleKind::by_name("Harmonic Minor").unwrap().of(G.sharp())));
+    }
+
+    #[test]
+    fn blues_a() {
+        assert_eq!(vec!["A", "C", "D", "Eb", "E", "G"],
+            spelled(ScaleKind::by_name("Blues").unwrap().of(A.natural())));
+    }
+
+    #[test]
+    fn simplest_spelling_prefers_flat_key() {
+        assert_eq!("Bb C D Eb F G A", ScaleKind::by_name("Major").unwrap().of_simplest_spelling(A.sharp()).string_representation());
+        assert_eq!("F# G# A# B C# D# E#", ScaleKind::by_name("Major").unwrap().of_simplest_spelling(F.sharp()).string_representation());
+    }
+
+    #[test]
+    fn simplest_spelling_of_lydian() {
+        assert_eq!("Db Eb F G Ab Bb C", ScaleKind::by_name("Lydian").unwrap().of_simplest_spelling(C.sharp()).string_representation());
+    }
+
+    #[test]
+    fn chromatic_has_twelve_notes() {
+        assert_eq!(12, ScaleKind::by_name("Chromatic").unwrap().note_cnt());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::guitar::fretboard::{Fretboard, Point, StringName};
+use crate::guitar::region::Region;
+use crate::music::chord::{Chord, ChordQuality};
+use crate::music::note::NoteName;
+use crate::music::scale;
+
+use super::scale_map;
+
+/// CAGED 指型 The five open chord shapes moved up the neck, in the order they follow each other going up
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum CagedShape {
+    C, A, G, E, D,
+}
+
+/// Frets of a shape in standard tuning, string 1 first, None for a muted string
+type ShapeFrets = [Option<u8>; 6];
+
+impl CagedShape {
+    pub const ALL : [CagedShape; 5] = [CagedShape::C, CagedShape::A, CagedShape::G, CagedShape::E, CagedShape::D];
+
+    pub fn string_representation(&self) -> &'static str {
+        match self {
+            CagedShape::C => "C shape",
+            CagedShape::A => "A shape",
+            CagedShape::G => "G shape",
+            CagedShape::E => "E shape",
+            CagedShape::D => "D shape",
+        }
+    }
+
+    /// The shape after this one going up the neck, D wrapping around to C
+    pub fn next(&self) -> CagedShape {
+        let index = CagedShape::ALL.iter().position(|shape| shape == self).unwrap_or(0);
+        CagedShape::ALL[(index + 1) % CagedShape::ALL.len()]
+    }
+
+    /// The open chord the shape comes from, and the string and fret of its lowest root
+    fn open_chord(&self, is_minor : bool) -> (ShapeFrets, StringName, u8) {
+        match (self, is_minor) {
+            // x32010, x3101x
+            (CagedShape::C, false) => ([Some(0), Some(1), Some(0), Some(2), Some(3), None], 5, 3),
+            (CagedShape::C, true) => ([None, Some(1), Some(0), Some(1), Some(3), None], 5, 3),
+            // x02220, x02210
+            (CagedShape::A, false) => ([Some(0), Some(2), Some(2), Some(2), Some(0), None], 5, 0),
+            (CagedShape::A, true) => ([Some(0), Some(1), Some(2), Some(2), Some(0), None], 5, 0),
+            // 320003, 310033
+            (CagedShape::G, false) => ([Some(3), Some(0), Some(0), Some(0), Some(2), Some(3)], 6, 3),
+            (CagedShape::G, true) => ([Some(3), Some(3), Some(0), Some(0), Some(1), Some(3)], 6, 3),
+            // 022100, 022000
+            (CagedShape::E, false) => ([Some(0), Some(0), Some(1), Some(2), Some(2), Some(0)], 6, 0),
+            (CagedShape::E, true) => ([Some(0), Some(0), Some(0), Some(2), Some(2), Some(0)], 6, 0),
+            // xx0232, xx0231
+            (CagedShape::D, false) => ([Some(2), Some(3), Some(2), Some(0), None, None], 4, 0),
+            (CagedShape::D, true) => ([Some(1), Some(3), Some(2), Some(0), None, None], 4, 0),
+        }
+    }
+}
+
+/// 把位 A CAGED shape of a chord at one place on the neck, with the notes of the chord's scale around it
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct CagedPosition {
+    shape : CagedShape,
+    chord_points : Vec<Point>,
+    /// The major scale of a major chord, the natural minor scale of a minor one, one fret around the shape
+    scale_points : Vec<Point>,
+    lowest_fret : u8,
+    highest_fret : u8,
+}
+
+impl CagedPosition {
+    pub fn shape(&self) -> CagedShape {
+        self.shape
+    }
+
+    pub fn chord_points(&self) -> &[Point] {
+        &self.chord_points
+    }
+
+    pub fn scale_points(&self) -> &[Point] {
+        &self.scale_points
+    }
+
+    /// Lowest sounding fret of the chord shape, open strings counting as their nut or capo fret
+    pub fn lowest_fret(&self) -> u8 {
+        self.lowest_fret
+    }
+
+    pub fn highest_fret(&self) -> u8 {
+        self.highest_fret
+    }
+}
+
+/// CAGED 系统 Every CAGED shape of a major or minor chord fitting on the fretboard, from the nut up; in order C, A, G, E, D
+/// as they follow each other. The shapes come from standard tuning, so they only fit tunings with its intervals between
+/// strings, like Eb standard; other chords and tunings have none.
+pub fn caged_positions(fretboard : &Fretboard, chord : &Chord) -> Vec<CagedPosition> {
+    let (is_minor, scale) = match chord.quality() {
+        ChordQuality::Major => (false, scale::major_scale_of(chord.root())),
+        ChordQuality::Minor => (true, scale::minor_scale_of(chord.root())),
+        _ => return Vec::new(),
+    };
+    if fretboard.string_name_vec().len() != 6 {
+        return Vec::new();
+    }
+    let chord_tones = chord.note_names();
+
+    let mut positions = Vec::new();
+    for shape in CagedShape::ALL {
+        let (open_frets, root_string, open_root_fret) = shape.open_chord(is_minor);
+        // Every fret the shape's root lands on the chord's root, the shape moved up as much
+        let root_frets : Vec<u8> = fretboard.points().into_iter()
+            .filter(|point| *point.on_string() == root_string)
+            .filter(|point| fretboard.note_of_point(point).is_ok_and(|note| note.note_name() == chord.root()))
+            .map(|point| fretboard.sounding_fret_of_point(&point))
+            .filter(|fret| *fret >= open_root_fret)
+            .collect();
+        for root_fret in root_frets {
+            if let Some(position) = caged_position_of(fretboard, shape, &open_frets, root_fret - open_root_fret, &chord_tones, &scale) {
+                positions.push(position);
+            }
+        }
+    }
+    positions.sort_by_key(|position| (position.lowest_fret, position.highest_fret));
+    positions
+}
+
+/// The shape moved up `shift` frets, if every point of it is on the fretboard and sounds a chord tone
+fn caged_position_of(fretboard : &Fretboard, shape : CagedShape, open_frets : &ShapeFrets, shift : u8,
+                     chord_tones : &[NoteName], scale : &[NoteName]) -> Option<CagedPosition> {
+    let mut chord_points = Vec::new();
+    let mut sounding_frets = Vec::new();
+    for (string, open_fret) in fretboard.string_name_vec().into_iter().zip(open_frets) {
+        let Some(open_fret) = open_fret else {
+            continue;
+        };
+        let fret = open_fret + shift;
+        // The fret the open strings sound at is played open
+        let point = if fret == fretboard.sounding_fret_of_point(&Point::of(string, 0).ok()?) {
+            Point::of(string, 0).ok()?
+        } else {
+            Point::of(string, fret).ok()?
+        };
+        if !fretboard.contains(&point)
+            || !fretboard.note_of_point(&point).is_ok_and(|note| chord_tones.contains(&note.note_name())) {
+            return None;
+        }
+        chord_points.push(point);
+        sounding_frets.push(fret);
+    }
+
+    let lowest_fret = *sounding_frets.iter().min()?;
+    let highest_fret = *sounding_frets.iter().max()?;
+    let scale_points = scale_map::scale_notes_in_region(fretboard, scale, &Region::of_frets(lowest_fret.saturating_sub(1)..=highest_fret + 1));
+    Some(CagedPosition { shape, chord_points, scale_points, lowest_fret, highest_fret })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::guitar::capo::Capo;
+    use crate::guitar::tuning::Tuning;
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn shapes_and_lowest_frets(positions : &[CagedPosition]) -> Vec<(CagedShape, u8)> {
+        positions.iter().map(|position| (position.shape(), position.lowest_fret())).collect()
+    }
+
+    #[test]
+    fn c_major_up_the_neck() {
+        let positions = caged_positions(&Fretboard::of_fret_cnt(16), &Chord::of(C.natural(), ChordQuality::Major));
+        assert_eq!(
+            vec![(CagedShape::C, 0), (CagedShape::A, 3), (CagedShape::G, 5), (CagedShape::E, 8), (CagedShape::D, 10), (CagedShape::C, 12)],
+            shapes_and_lowest_frets(&positions));
+        // x35553
+        let a_shape = &positions[1];
+        assert_eq!(5, a_shape.chord_points().len());
+        assert!(a_shape.chord_points().contains(&Point::of(5, 3).unwrap()));
+        assert!(a_shape.chord_points().contains(&Point::of(3, 5).unwrap()));
+        for point in a_shape.scale_points() {
+            assert!((2..=6).contains(point.behind_fret()));
+        }
+    }
+
+    #[test]
+    fn shapes_follow_each_other() {
+        let mut shape = CagedShape::C;
+        for _ in 0..5 {
+            shape = shape.next();
+        }
+        assert_eq!(CagedShape::C, shape);
+        assert_eq!(CagedShape::A, CagedShape::C.next());
+    }
+
+    #[test]
+    fn minor_chords() {
+        let positions = caged_positions(&Fretboard::of_fret_cnt(13), &Chord::of(A.natural(), ChordQuality::Minor));
+        assert_eq!(
+            vec![(CagedShape::A, 0), (CagedShape::G, 2), (CagedShape::E, 5), (CagedShape::D, 7), (CagedShape::C, 9)],
+            shapes_and_lowest_frets(&positions));
+        // Natural minor scale, no F#
+        let scale = scale::minor_scale_of(A.natural());
+        let fretboard = Fretboard::of_fret_cnt(13);
+        for point in positions[0].scale_points() {
+            assert!(scale.contains(&fretboard.note_of_point(point).unwrap().note_name()));
+        }
+    }
+
+    #[test]
+    fn open_shapes_under_capo() {
+        let fretboard = Fretboard::of_fret_cnt(13).with_capo(Some(Capo::full(2).unwrap())).unwrap();
+        let positions = caged_positions(&fretboard, &Chord::of(D.natural(), ChordQuality::Major));
+        let c_shape = positions.iter().find(|position| position.shape() == CagedShape::C).unwrap();
+        // x32010 from the capo
+        assert!(c_shape.chord_points().contains(&Point::of(1, 0).unwrap()));
+        assert!(c_shape.chord_points().contains(&Point::of(5, 5).unwrap()));
+    }
+
+    #[test]
+    fn other_chords_and_tunings_have_none() {
+        assert!(caged_positions(&Fretboard::of_standard(), &Chord::of(C.natural(), ChordQuality::Dominant7)).is_empty());
+        let open_g = Fretboard::of_tuning(Tuning::preset("Open G").unwrap(), 13);
+        assert!(caged_positions(&open_g, &Chord::of(C.natural(), ChordQuality::Major)).is_empty());
+    }
+}
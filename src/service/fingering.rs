@@ -0,0 +1,320 @@
+use std::ops::RangeInclusive;
+
+use crate::guitar::fretboard::{Fretboard, Point, StringName};
+use crate::music::note::{Note, MIDDLE_C};
+use crate::music::scale::Scale;
+
+/// Fingers of the fretting hand, index to pinky
+const FINGER_CNT : u8 = 4;
+
+/// 指法音 A point of a fingering with the finger suggested for it, 1 for the index
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct FingeredPoint {
+    point : Point,
+    /// None for an open string
+    finger : Option<u8>,
+}
+
+impl FingeredPoint {
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    pub fn finger(&self) -> Option<u8> {
+        self.finger
+    }
+}
+
+/// 音阶指法 One position of a scale, played in order from the lowest note up
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ScaleFingering {
+    /// Degree of the scale the fingering starts on, 1 for the root
+    start_degree : usize,
+    fingered_points : Vec<FingeredPoint>,
+}
+
+impl ScaleFingering {
+    pub fn start_degree(&self) -> usize {
+        self.start_degree
+    }
+
+    pub fn fingered_points(&self) -> &[FingeredPoint] {
+        &self.fingered_points
+    }
+
+    /// In playing order, the lowest note first
+    pub fn points(&self) -> Vec<Point> {
+        self.fingered_points.iter().map(FingeredPoint::point).collect()
+    }
+
+    /// Lowest and highest sounding frets, open strings counting as their nut or capo fret
+    pub fn fret_range(&self, fretboard : &Fretboard) -> RangeInclusive<u8> {
+        let frets = self.fingered_points.iter().map(|fingered_point| fretboard.sounding_fret_of_point(&fingered_point.point));
+        let (lowest, highest) = frets.fold((u8::MAX, 0), |(lowest, highest), fret| (lowest.min(fret), highest.max(fret)));
+        lowest..=highest
+    }
+}
+
+/// 指法种类 The kinds of scale fingerings
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum FingeringKind {
+    ThreeNotesPerString,
+    PentatonicBox,
+    SingleOctave,
+}
+
+impl FingeringKind {
+    pub const ALL : [FingeringKind; 3] = [FingeringKind::ThreeNotesPerString, FingeringKind::PentatonicBox, FingeringKind::SingleOctave];
+
+    pub fn string_representation(&self) -> &'static str {
+        match self {
+            FingeringKind::ThreeNotesPerString => "3 notes per string",
+            FingeringKind::PentatonicBox => "Pentatonic boxes",
+            FingeringKind::SingleOctave => "Single octave",
+        }
+    }
+
+    /// The fingerings of this kind of the scale in the fret window, from the nut up
+    pub fn fingerings(&self, fretboard : &Fretboard, scale : &Scale, frets : RangeInclusive<u8>) -> Vec<ScaleFingering> {
+        match self {
+            FingeringKind::ThreeNotesPerString => three_notes_per_string(fretboard, scale, frets),
+            FingeringKind::PentatonicBox => pentatonic_boxes(fretboard, scale, frets),
+            FingeringKind::SingleOctave => single_octave_shapes(fretboard, scale, frets),
+        }
+    }
+}
+
+/// 三音一弦 The seven positions of a seven-note scale with three notes on each string, one starting on each degree of the
+/// lowest string, each at the lowest place it fits in the fret window, from the nut up. Fingers follow the frets on each string,
+/// the index on its first note. Other scales have none.
+pub fn three_notes_per_string(fretboard : &Fretboard, scale : &Scale, frets : RangeInclusive<u8>) -> Vec<ScaleFingering> {
+    if scale.note_cnt() != 7 {
+        return Vec::new();
+    }
+    notes_per_string_positions(fretboard, scale, 3, frets, |string_frets, _| {
+        let first_fret = string_frets.iter().copied().min().unwrap_or(0);
+        string_frets.iter().map(|fret| (fret - first_fret + 1).min(FINGER_CNT)).collect()
+    })
+}
+
+/// 五声音阶把位 The five boxes of a five-note scale like the pentatonics, with two notes on each string, one starting on each
+/// degree of the lowest string, each at the lowest place it fits in the fret window, from the nut up.
+/// One finger per fret from the lowest fret of the box. Other scales have none.
+pub fn pentatonic_boxes(fretboard : &Fretboard, scale : &Scale, frets : RangeInclusive<u8>) -> Vec<ScaleFingering> {
+    if scale.note_cnt() != 5 {
+        return Vec::new();
+    }
+    notes_per_string_positions(fretboard, scale, 2, frets, |string_frets, box_first_fret| {
+        string_frets.iter().map(|fret| (fret - box_first_fret + 1).min(FINGER_CNT)).collect()
+    })
+}
+
+/// 单八度指型 The scale from each root in the fret window up to the root an octave higher, in one hand position:
+/// the middle finger on the root when it fits, otherwise the index, one finger per fret. Lowest roots first, lower on the neck first.
+pub fn single_octave_shapes(fretboard : &Fretboard, scale : &Scale, frets : RangeInclusive<u8>) -> Vec<ScaleFingering> {
+    let mut roots : Vec<(Point, Note)> = fretboard.positions_of_pitch_class(&scale.root()).into_iter()
+        .filter(|point| frets.contains(&fretboard.sounding_fret_of_point(point)))
+        .filter_map(|point| Some((point, fretboard.note_of_point(&point).ok()?)))
+        .collect();
+    roots.sort_by_key(|(point, note)| (note.minus_note(&MIDDLE_C).semitone_diff(), fretboard.sounding_fret_of_point(point)));
+
+    let mut shapes = Vec::new();
+    for (root_point, root_note) in roots {
+        let root_fret = fretboard.sounding_fret_of_point(&root_point);
+        // Middle finger on the root first, then the index; the index on fret 1 at least for an open root
+        let hand_positions = [root_fret.checked_sub(1).filter(|fret| *fret != 0), Some(root_fret.max(1))];
+        let shape = hand_positions.into_iter().flatten().find_map(|first_fret| {
+            let hand_frets = first_fret..=first_fret + FINGER_CNT - 1;
+            single_octave_shape(fretboard, scale, root_point, &root_note, hand_frets)
+                .filter(|points| points.iter().all(|point| frets.contains(&fretboard.sounding_fret_of_point(point))))
+                .map(|points| ScaleFingering {
+                    start_degree: 1,
+                    fingered_points: points.into_iter()
+                        .map(|point| FingeredPoint {
+                            point,
+                            finger: (*point.behind_fret() != 0).then(|| fretboard.sounding_fret_of_point(&point) - first_fret + 1),
+                        })
+                        .collect(),
+                })
+        });
+        shapes.extend(shape);
+    }
+    shapes
+}
+
+/// The points of the octave from the root, staying on a string while the next note is in the hand's frets
+fn single_octave_shape(fretboard : &Fretboard, scale : &Scale, root_point : Point, root_note : &Note,
+                       hand_frets : RangeInclusive<u8>) -> Option<Vec<Point>> {
+    let mut points = vec![root_point];
+    let mut string = *root_point.on_string();
+    let mut note = root_note.clone();
+    for _ in 0..scale.note_cnt() {
+        note = next_scale_note(scale, &note)?;
+        let in_hand = |string : StringName| point_of_note_on_string(fretboard, string, &note)
+            .filter(|point| *point.behind_fret() == 0 || hand_frets.contains(&fretboard.sounding_fret_of_point(point)));
+        let point = in_hand(string).or_else(|| {
+            string = string.checked_sub(1)?;
+            in_hand(string)
+        })?;
+        points.push(point);
+    }
+    Some(points)
+}
+
+/// Positions of `notes_per_string` notes on each string, the lowest string first, finding each one's fingers from the frets
+/// on a string and the first fret of the position.
+fn notes_per_string_positions(fretboard : &Fretboard, scale : &Scale, notes_per_string : usize, frets : RangeInclusive<u8>,
+                              fingers_of : impl Fn(&[u8], u8) -> Vec<u8>) -> Vec<ScaleFingering> {
+    let Some(lowest_string) = fretboard.string_name_vec().last().copied() else {
+        return Vec::new();
+    };
+
+    let mut positions = Vec::new();
+    for start_degree in 1..=scale.note_cnt() {
+        let Some(start_note_name) = scale.degree(start_degree) else {
+            continue;
+        };
+        let mut start_points : Vec<Point> = fretboard.positions_of_pitch_class(&start_note_name).into_iter()
+            .filter(|point| *point.on_string() == lowest_string)
+            .collect();
+        start_points.sort_by_key(|point| fretboard.sounding_fret_of_point(point));
+
+        let position = start_points.into_iter().find_map(|start_point| {
+            let points = notes_per_string_points(fretboard, scale, notes_per_string, start_point)?;
+            let sounding_frets : Vec<u8> = points.iter().map(|point| fretboard.sounding_fret_of_point(point)).collect();
+            if !sounding_frets.iter().all(|fret| frets.contains(fret)) {
+                return None;
+            }
+            let first_fret = sounding_frets.iter().zip(&points)
+                .filter(|(_, point)| *point.behind_fret() != 0)
+                .map(|(fret, _)| *fret)
+                .min()
+                .unwrap_or(0);
+
+            let mut fingered_points = Vec::new();
+            for string_points in points.chunks(notes_per_string) {
+                let string_frets : Vec<u8> = string_points.iter()
+                    .filter(|point| *point.behind_fret() != 0)
+                    .map(|point| fretboard.sounding_fret_of_point(point))
+                    .collect();
+                let mut fingers = fingers_of(&string_frets, first_fret).into_iter();
+                for point in string_points {
+                    let finger = if *point.behind_fret() == 0 { None } else { fingers.next() };
+                    fingered_points.push(FingeredPoint { point: *point, finger });
+                }
+            }
+            Some(ScaleFingering { start_degree, fingered_points })
+        });
+        positions.extend(position);
+    }
+    positions.sort_by_key(|position| *position.fret_range(fretboard).start());
+    positions
+}
+
+/// Climbs the scale from the start point, `notes_per_string` notes on each string down to string 1
+fn notes_per_string_points(fretboard : &Fretboard, scale : &Scale, notes_per_string : usize, start_point : Point) -> Option<Vec<Point>> {
+    let mut points = vec![start_point];
+    let mut note = fretboard.note_of_point(&start_point).ok()?;
+    for string in (1..=*start_point.on_string()).rev() {
+        let string_note_cnt = if string == *start_point.on_string() { notes_per_string - 1 } else { notes_per_string };
+        for _ in 0..string_note_cnt {
+            note = next_scale_note(scale, &note)?;
+            points.push(point_of_note_on_string(fretboard, string, &note)?);
+        }
+    }
+    Some(points)
+}
+
+/// The next note of the scale going up
+fn next_scale_note(scale : &Scale, note : &Note) -> Option<Note> {
+    (1..=12).find_map(|semitone_diff| note.add_semitones(semitone_diff).ok()
+        .filter(|next_note| scale.contains(&next_note.note_name())))
+}
+
+/// The point sounding exactly the note on the string, if any
+fn point_of_note_on_string(fretboard : &Fretboard, string : StringName, note : &Note) -> Option<Point> {
+    let open_point = Point::of(string, 0).ok()?;
+    let semitone_diff = note.minus_note(&fretboard.open_note_of_string(string).ok()?).semitone_diff();
+    let point = match u8::try_from(semitone_diff).ok()? {
+        0 => open_point,
+        semitone_diff => Point::of(string, fretboard.sounding_fret_of_point(&open_point).checked_add(semitone_diff)?).ok()?,
+    };
+    fretboard.contains(&point).then_some(point)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::note::NaturalNoteName::*;
+    use crate::music::scale_catalog::ScaleKind;
+    use super::*;
+
+    fn frets_and_fingers(fingering : &ScaleFingering) -> Vec<(u8, Option<u8>)> {
+        fingering.fingered_points().iter().map(|fingered_point| (*fingered_point.point().behind_fret(), fingered_point.finger())).collect()
+    }
+
+    fn scale(name : &str, root : crate::music::note::NoteName) -> Scale {
+        ScaleKind::by_name(name).unwrap().of(root)
+    }
+
+    #[test]
+    fn g_major_three_notes_per_string() {
+        let fretboard = Fretboard::of_fret_cnt(22);
+        let positions = three_notes_per_string(&fretboard, &scale("Major", G.natural()), 0..=21);
+        assert_eq!(7, positions.len());
+        let first = positions.iter().find(|position| position.start_degree() == 1).unwrap();
+        assert_eq!(18, first.points().len());
+        // G A B on the low E string, from fret 3
+        assert_eq!(vec![(3, Some(1)), (5, Some(3)), (7, Some(4))], frets_and_fingers(first)[..3].to_vec());
+        // Ascending notes
+        let notes : Vec<Note> = first.points().iter().map(|point| fretboard.note_of_point(point).unwrap()).collect();
+        assert!(notes.windows(2).all(|pair| pair[1].minus_note(&pair[0]).semitone_diff() > 0));
+    }
+
+    #[test]
+    fn a_minor_pentatonic_boxes() {
+        let fretboard = Fretboard::of_fret_cnt(22);
+        let boxes = pentatonic_boxes(&fretboard, &scale("Minor Pentatonic", A.natural()), 4..=17);
+        assert_eq!(5, boxes.len());
+        let first_box = &boxes[0];
+        assert_eq!(1, first_box.start_degree());
+        assert_eq!(5..=8, first_box.fret_range(&fretboard));
+        assert_eq!(
+            vec![(5, Some(1)), (8, Some(4)), (5, Some(1)), (7, Some(3)), (5, Some(1)), (7, Some(3)),
+                 (5, Some(1)), (7, Some(3)), (5, Some(1)), (8, Some(4)), (5, Some(1)), (8, Some(4))],
+            frets_and_fingers(first_box));
+        // Box 2 from C on the low E string
+        assert_eq!(2, boxes[1].start_degree());
+        assert_eq!(7..=10, boxes[1].fret_range(&fretboard));
+    }
+
+    #[test]
+    fn positions_stay_in_the_window() {
+        let fretboard = Fretboard::of_fret_cnt(22);
+        let positions = three_notes_per_string(&fretboard, &scale("Major", G.natural()), 0..=8);
+        assert!(!positions.is_empty() && positions.len() < 7);
+        for position in &positions {
+            assert!(position.fret_range(&fretboard).end() <= &8);
+        }
+        assert!(pentatonic_boxes(&fretboard, &scale("Major", G.natural()), 0..=21).is_empty());
+    }
+
+    #[test]
+    fn c_major_single_octave() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let shapes = single_octave_shapes(&fretboard, &scale("Major", C.natural()), 1..=12);
+        // From C3 on the fifth string, middle finger on the root
+        assert_eq!(
+            vec![(3, Some(2)), (5, Some(4)), (2, Some(1)), (3, Some(2)), (5, Some(4)), (2, Some(1)), (4, Some(3)), (5, Some(4))],
+            frets_and_fingers(&shapes[0]));
+        assert!(shapes.iter().all(|shape| shape.points().len() == 8));
+        // From C3 on the sixth string, fret 8
+        assert!(shapes.iter().any(|shape| shape.points()[0] == Point::of(6, 8).unwrap()));
+    }
+}
@@ -1 +1,9 @@
-pub mod scale_map;
\ No newline at end of file
+pub mod scale_map;
+pub mod recognition;
+pub mod quiz;
+pub mod spaced_repetition;
+pub mod saved_state;
+pub mod note_label;
+pub mod voicing;
+pub mod caged;
+pub mod fingering;
\ No newline at end of file
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use crate::music::note::{NaturalNoteName, Note, NoteName};
+
+/// 标注方式 How notes overlaid on the fretboard are labelled
+#[derive(Clone, Copy)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub enum LabelMode {
+    /// e.g. "Eb"
+    PitchClass,
+    /// e.g. "Eb4"
+    #[default]
+    ScientificPitch,
+    /// Relative to the root, e.g. "b3"
+    ScaleDegree,
+    /// From the root, e.g. "m3", the root itself being "R"
+    Interval,
+    /// 首调唱名 Relative to the root, chromatic syllables, e.g. "Me"
+    MovableDo,
+    /// 固定唱名 C is always Do, e.g. "Mib"
+    FixedDo,
+    /// 简谱 Degree numbers relative to the root, octaves around the root's fourth octave marked ' above and , below, e.g. "b3'"
+    Jianpu,
+    /// Only dots, for self-testing
+    Blank,
+}
+
+impl LabelMode {
+    pub const ALL : [LabelMode; 8] = [
+        LabelMode::PitchClass, LabelMode::ScientificPitch, LabelMode::ScaleDegree, LabelMode::Interval,
+        LabelMode::MovableDo, LabelMode::FixedDo, LabelMode::Jianpu, LabelMode::Blank,
+    ];
+
+    pub fn string_representation(&self) -> &'static str {
+        match self {
+            LabelMode::PitchClass => "Note name",
+            LabelMode::ScientificPitch => "Scientific pitch",
+            LabelMode::ScaleDegree => "Scale degree",
+            LabelMode::Interval => "Interval",
+            LabelMode::MovableDo => "Movable do",
+            LabelMode::FixedDo => "Fixed do",
+            LabelMode::Jianpu => "简谱 Numbered",
+            LabelMode::Blank => "Blank",
+        }
+    }
+
+    /// The label of the note, in a scale or key on `root` for the relative modes.
+    pub fn label(&self, note : &Note, root : &NoteName) -> String {
+        let interval = note.note_name().interval_above(root);
+        match self {
+            LabelMode::PitchClass => note.note_name().string_representation(),
+            LabelMode::ScientificPitch => note.clone().string_representation(),
+            LabelMode::ScaleDegree => interval.degree_representation(),
+            LabelMode::Interval if interval.semitone_diff() == 0 && interval.degree_diff() == 0 => "R".to_owned(),
+            LabelMode::Interval => interval.string_representation(),
+            LabelMode::MovableDo => {
+                let degree = interval.degree_representation();
+                movable_do_syllable(&degree).map_or(degree, str::to_owned)
+            },
+            LabelMode::FixedDo => fixed_do_syllable(&note.note_name()),
+            LabelMode::Jianpu => {
+                // Octaves counted from the root on octave 4, by the interval's semitones so that e.g. B#3 is an octave below C4
+                let root_note = root.clone().on_octave(4);
+                let octave_diff = (note.minus_note(&root_note).semitone_diff() as i32 - interval.semitone_diff() as i32).div_euclid(12);
+                let octave_marks = if octave_diff >= 0 { "'".repeat(octave_diff as usize) } else { ",".repeat(octave_diff.unsigned_abs() as usize) };
+                interval.degree_representation() + &octave_marks
+            },
+            LabelMode::Blank => String::new(),
+        }
+    }
+}
+
+/// Chromatic movable-do syllables, raised degrees ending in "i", lowered ones in "e" or "a"
+fn movable_do_syllable(degree : &str) -> Option<&'static str> {
+    Some(match degree {
+        "1" => "Do",
+        "#1" => "Di",
+        "b2" => "Ra",
+        "2" => "Re",
+        "#2" => "Ri",
+        "b3" => "Me",
+        "3" => "Mi",
+        "4" => "Fa",
+        "#4" => "Fi",
+        "b5" => "Se",
+        "5" => "Sol",
+        "#5" => "Si",
+        "b6" => "Le",
+        "6" => "La",
+        "#6" => "Li",
+        "b7" => "Te",
+        "7" => "Ti",
+        _ => return None,
+    })
+}
+
+fn fixed_do_syllable(note_name : &NoteName) -> String {
+    let syllable = match note_name.natural_note_name() {
+        NaturalNoteName::C => "Do",
+        NaturalNoteName::D => "Re",
+        NaturalNoteName::E => "Mi",
+        NaturalNoteName::F => "Fa",
+        NaturalNoteName::G => "Sol",
+        NaturalNoteName::A => "La",
+        NaturalNoteName::B => "Si",
+    };
+    let accidental = note_name.accidental_cnt();
+    let accidental_str = if accidental >= 0 { "#".repeat(accidental as usize) } else { "b".repeat(accidental.unsigned_abs() as usize) };
+    syllable.to_owned() + &accidental_str
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn labels(mode : LabelMode, root : NoteName) -> Vec<String> {
+        [E.flat().on_octave(4), G.natural().on_octave(4), C.natural().on_octave(5), F.sharp().on_octave(3)].iter()
+            .map(|note| mode.label(note, &root))
+            .collect()
+    }
+
+    #[test]
+    fn absolute_labels() {
+        assert_eq!(vec!["Eb", "G", "C", "F#"], labels(LabelMode::PitchClass, C.natural()));
+        assert_eq!(vec!["Eb4", "G4", "C5", "F#3"], labels(LabelMode::ScientificPitch, C.natural()));
+        assert_eq!(vec!["Mib", "Sol", "Do", "Fa#"], labels(LabelMode::FixedDo, A.natural()));
+        assert_eq!(vec!["", "", "", ""], labels(LabelMode::Blank, C.natural()));
+    }
+
+    #[test]
+    fn labels_relative_to_root() {
+        assert_eq!(vec!["b3", "5", "1", "#4"], labels(LabelMode::ScaleDegree, C.natural()));
+        assert_eq!(vec!["m3", "P5", "R", "A4"], labels(LabelMode::Interval, C.natural()));
+        assert_eq!(vec!["Me", "Sol", "Do", "Fi"], labels(LabelMode::MovableDo, C.natural()));
+        assert_eq!(vec!["4", "6", "2", "#5"], labels(LabelMode::ScaleDegree, B.flat()));
+    }
+
+    #[test]
+    fn jianpu_octave_marks() {
+        assert_eq!(vec!["b3", "5", "1'", "#4,"], labels(LabelMode::Jianpu, C.natural()));
+    }
+}
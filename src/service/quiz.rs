@@ -0,0 +1,470 @@
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::guitar::fretboard::{Fretboard, Point, StringName};
+use crate::music::note::{Note, NoteName};
+use super::spaced_repetition::{AnswerTiming, PracticeSchedule};
+
+/// 测验计分 Score of a quiz round
+#[derive(Clone, Copy)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct QuizScore {
+    correct_cnt : u32,
+    incorrect_cnt : u32,
+    streak : u32,
+    best_streak : u32,
+}
+
+impl QuizScore {
+    pub fn record(&mut self, is_correct : bool) {
+        if is_correct {
+            self.correct_cnt = self.correct_cnt.saturating_add(1);
+            self.streak = self.streak.saturating_add(1);
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.incorrect_cnt = self.incorrect_cnt.saturating_add(1);
+            self.streak = 0;
+        }
+    }
+
+    pub fn correct_cnt(&self) -> u32 {
+        self.correct_cnt
+    }
+
+    pub fn incorrect_cnt(&self) -> u32 {
+        self.incorrect_cnt
+    }
+
+    /// Correct answers in a row
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    pub fn best_streak(&self) -> u32 {
+        self.best_streak
+    }
+
+    /// Ratio of correct answers, None before the first answer.
+    pub fn accuracy(&self) -> Option<f32> {
+        // Counted in u64, the counts of a damaged save may add up beyond a u32
+        let answer_cnt = self.correct_cnt as u64 + self.incorrect_cnt as u64;
+        (answer_cnt != 0).then(|| self.correct_cnt as f32 / answer_cnt as f32)
+    }
+}
+
+/// 测验模式 The quiz modes
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub enum QuizMode {
+    FindNote,
+    NameNote,
+}
+
+/// 测验记录 Score of a finished round, kept in the quiz history
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct QuizRoundRecord {
+    pub mode : QuizMode,
+    /// Seconds since the Unix epoch
+    pub finished_at : u64,
+    pub score : QuizScore,
+}
+
+/// Where the note is to be found
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum FindNoteTarget {
+    OnString(StringName),
+    Everywhere,
+}
+
+/// 找音题 A note to find on the fretboard, once on a given string or everywhere it sounds.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct FindNoteQuestion {
+    note_name : NoteName,
+    target : FindNoteTarget,
+    answers : Vec<Point>,
+    found : Vec<Point>,
+}
+
+impl FindNoteQuestion {
+    /// The note on the candidate point the schedule picks, spelled with a sharp or a flat at random. None without candidates.
+    pub fn next(fretboard : &Fretboard, candidates : &[Point], is_everywhere : bool, schedule : &PracticeSchedule, now : u64, rng : &mut Rng)
+        -> Option<FindNoteQuestion> {
+        let point = schedule.next_point(fretboard, candidates, now, rng)?;
+        let integer_notation = fretboard.note_of_point(&point).ok()?.note_name().integer_notation();
+        let note_name = if rng.bool() {
+            NoteName::of_pitch_class_as_sharp(integer_notation)
+        } else {
+            NoteName::of_pitch_class_as_flat(integer_notation)
+        };
+        let target = if is_everywhere { FindNoteTarget::Everywhere } else { FindNoteTarget::OnString(*point.on_string()) };
+        let answers = fretboard.positions_of_pitch_class(&note_name).into_iter()
+            .filter(|answer| candidates.contains(answer))
+            .filter(|answer| match target {
+                FindNoteTarget::OnString(string) => *answer.on_string() == string,
+                FindNoteTarget::Everywhere => true,
+            })
+            .collect();
+        Some(FindNoteQuestion { note_name, target, answers, found: Vec::new() })
+    }
+
+    pub fn note_name(&self) -> &NoteName {
+        &self.note_name
+    }
+
+    pub fn target(&self) -> FindNoteTarget {
+        self.target
+    }
+
+    /// Points found so far
+    pub fn found(&self) -> &[Point] {
+        &self.found
+    }
+
+    /// Points still to be found, all of them for the everywhere target.
+    pub fn remaining_cnt(&self) -> usize {
+        match self.target {
+            FindNoteTarget::OnString(_) => usize::from(self.found.is_empty()),
+            FindNoteTarget::Everywhere => self.answers.len() - self.found.len(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.remaining_cnt() == 0
+    }
+
+    /// e.g. "G on string 5" or "every G"
+    pub fn string_representation(&self) -> String {
+        match self.target {
+            FindNoteTarget::OnString(string) => format!("{} on string {}", self.note_name.clone().string_representation(), string),
+            FindNoteTarget::Everywhere => format!("every {}", self.note_name.clone().string_representation()),
+        }
+    }
+}
+
+/// 答题反馈 Result of a click
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum FindNoteFeedback {
+    Correct,
+    /// Correct, but already found; not scored.
+    AlreadyFound,
+    /// The note sounding at the clicked point instead.
+    Incorrect { point : Point, note : Note },
+    /// Nothing sounds at the point, behind the capo or before a short string's nut; not scored.
+    Unplayable,
+    /// The point is outside the frets and strings asked about; not scored, whatever its note.
+    OutsideRegion,
+}
+
+/// 找音测验 "Find the note" quiz: clicks on the fretboard answer the question, a new one is asked once it is complete.
+pub struct FindNoteQuiz {
+    question : FindNoteQuestion,
+    is_everywhere : bool,
+    score : QuizScore,
+    last_feedback : Option<FindNoteFeedback>,
+}
+
+impl FindNoteQuiz {
+    /// Questions are asked on the candidate points only, None without candidates.
+    pub fn new(fretboard : &Fretboard, candidates : &[Point], is_everywhere : bool, schedule : &PracticeSchedule, now : u64, rng : &mut Rng)
+        -> Option<FindNoteQuiz> {
+        let question = FindNoteQuestion::next(fretboard, candidates, is_everywhere, schedule, now, rng)?;
+        Some(FindNoteQuiz { question, is_everywhere, score: QuizScore::default(), last_feedback: None })
+    }
+
+    pub fn question(&self) -> &FindNoteQuestion {
+        &self.question
+    }
+
+    pub fn score(&self) -> &QuizScore {
+        &self.score
+    }
+
+    pub fn last_feedback(&self) -> Option<&FindNoteFeedback> {
+        self.last_feedback.as_ref()
+    }
+
+    /// Scores the click and records it to the schedule: a miss counts against the asked note,
+    /// and against its positions on the asked string. Clicks off the candidate points are not scored.
+    pub fn answer(&mut self, fretboard : &Fretboard, candidates : &[Point], point : Point, schedule : &mut PracticeSchedule,
+                  timing : AnswerTiming, rng : &mut Rng) -> FindNoteFeedback {
+        let feedback = if self.question.found.contains(&point) {
+            FindNoteFeedback::AlreadyFound
+        } else if self.question.answers.contains(&point) {
+            self.question.found.push(point);
+            self.score.record(true);
+            schedule.record(fretboard, point, &self.question.note_name, true, timing);
+            FindNoteFeedback::Correct
+        } else if let Ok(note) = fretboard.note_of_point(&point) {
+            if candidates.contains(&point) {
+                self.score.record(false);
+                match self.question.target {
+                    FindNoteTarget::OnString(_) => for answer in &self.question.answers {
+                        schedule.record(fretboard, *answer, &self.question.note_name, false, timing);
+                    },
+                    FindNoteTarget::Everywhere => schedule.record_pitch_class(&self.question.note_name, false, timing),
+                }
+                FindNoteFeedback::Incorrect { point, note }
+            } else {
+                FindNoteFeedback::OutsideRegion
+            }
+        } else {
+            FindNoteFeedback::Unplayable
+        };
+
+        if self.question.is_complete()
+            && let Some(question) = FindNoteQuestion::next(fretboard, candidates, self.is_everywhere, schedule, timing.answered_at, rng) {
+            self.question = question;
+        }
+        self.last_feedback = Some(feedback.clone());
+        feedback
+    }
+}
+
+/// 升降号拼写 Which spellings of the black keys an answer may use
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub enum AccidentalSpelling {
+    Sharps,
+    Flats,
+    /// Either the sharp or the flat spelling, e.g. C# or Db
+    Both,
+}
+
+impl AccidentalSpelling {
+    pub const ALL : [AccidentalSpelling; 3] = [AccidentalSpelling::Sharps, AccidentalSpelling::Flats, AccidentalSpelling::Both];
+
+    /// Whether `answer` names the pitch class of `note_name` with an allowed spelling.
+    /// Naturals must be named as such, i.e. E is not accepted as Fb nor C as B#.
+    pub fn accepts(&self, answer : &NoteName, note_name : &NoteName) -> bool {
+        let integer_notation = note_name.integer_notation();
+        let is_spelled_as = |spelled : NoteName| -> bool {
+            answer.natural_note_name() == spelled.natural_note_name() && answer.accidental_cnt() == spelled.accidental_cnt()
+        };
+        let is_sharp_spelled = is_spelled_as(NoteName::of_pitch_class_as_sharp(integer_notation));
+        let is_flat_spelled = is_spelled_as(NoteName::of_pitch_class_as_flat(integer_notation));
+        match self {
+            AccidentalSpelling::Sharps => is_sharp_spelled,
+            AccidentalSpelling::Flats => is_flat_spelled,
+            AccidentalSpelling::Both => is_sharp_spelled || is_flat_spelled,
+        }
+    }
+
+    /// One note name per pitch class from C, spelled with sharps, or with flats if only they are accepted.
+    pub fn answer_choices(&self) -> Vec<NoteName> {
+        (0..12)
+            .map(|integer_notation| match self {
+                AccidentalSpelling::Flats => NoteName::of_pitch_class_as_flat(integer_notation),
+                _ => NoteName::of_pitch_class_as_sharp(integer_notation),
+            })
+            .collect()
+    }
+
+    pub fn string_representation(&self) -> &'static str {
+        match self {
+            AccidentalSpelling::Sharps => "Sharps",
+            AccidentalSpelling::Flats => "Flats",
+            AccidentalSpelling::Both => "Sharps or flats",
+        }
+    }
+}
+
+/// 答题反馈 Result of naming a note
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum NameNoteFeedback {
+    Correct { point : Point, note : Note },
+    Incorrect { point : Point, note : Note, answer : NoteName },
+}
+
+/// 认音测验 "Name the note" quiz: a point is shown, its note is to be named.
+pub struct NameNoteQuiz {
+    point : Point,
+    spelling : AccidentalSpelling,
+    score : QuizScore,
+    last_feedback : Option<NameNoteFeedback>,
+}
+
+impl NameNoteQuiz {
+    /// Points are asked among the candidates only, None without candidates.
+    pub fn new(fretboard : &Fretboard, candidates : &[Point], spelling : AccidentalSpelling, schedule : &PracticeSchedule, now : u64, rng : &mut Rng)
+        -> Option<NameNoteQuiz> {
+        let point = schedule.next_point(fretboard, candidates, now, rng)?;
+        Some(NameNoteQuiz { point, spelling, score: QuizScore::default(), last_feedback: None })
+    }
+
+    /// The point whose note is asked
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    pub fn spelling(&self) -> AccidentalSpelling {
+        self.spelling
+    }
+
+    /// Answers from now on are checked against the new spelling.
+    pub fn set_spelling(&mut self, spelling : AccidentalSpelling) {
+        self.spelling = spelling;
+    }
+
+    pub fn score(&self) -> &QuizScore {
+        &self.score
+    }
+
+    pub fn last_feedback(&self) -> Option<&NameNoteFeedback> {
+        self.last_feedback.as_ref()
+    }
+
+    /// Scores the answer, records it to the schedule and asks the next point, a different one if there is.
+    pub fn answer(&mut self, fretboard : &Fretboard, candidates : &[Point], answer : NoteName, schedule : &mut PracticeSchedule,
+                  timing : AnswerTiming, rng : &mut Rng) -> Option<&NameNoteFeedback> {
+        let point = self.point;
+        let note = fretboard.note_of_point(&point).ok()?;
+        let is_correct = self.spelling.accepts(&answer, &note.note_name());
+        self.score.record(is_correct);
+        schedule.record(fretboard, point, &note.note_name(), is_correct, timing);
+        self.last_feedback = Some(if is_correct {
+            NameNoteFeedback::Correct { point, note }
+        } else {
+            NameNoteFeedback::Incorrect { point, note, answer }
+        });
+
+        let other_candidates : Vec<Point> = candidates.iter().filter(|candidate| **candidate != point).cloned().collect();
+        if let Some(next_point) = schedule.next_point(fretboard, &other_candidates, timing.answered_at, rng) {
+            self.point = next_point;
+        }
+        self.last_feedback.as_ref()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::guitar::capo::Capo;
+    use crate::guitar::region::Region;
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    const TIMING : AnswerTiming = AnswerTiming { response_time: Duration::from_secs(1), answered_at: 0 };
+
+    #[test]
+    fn score_keeps_best_streak() {
+        let mut score = QuizScore::default();
+        for is_correct in [true, true, false, true] {
+            score.record(is_correct);
+        }
+        assert_eq!((3, 1, 1, 2), (score.correct_cnt(), score.incorrect_cnt(), score.streak(), score.best_streak()));
+        assert_eq!(Some(0.75), score.accuracy());
+    }
+
+    #[test]
+    fn damaged_score_counts_do_not_overflow() {
+        let mut score : QuizScore = ron::from_str("(correct_cnt: 4294967295, incorrect_cnt: 1, streak: 4294967295, best_streak: 0)").unwrap();
+        assert!(score.accuracy().unwrap() > 0.99);
+        score.record(true);
+        assert_eq!((u32::MAX, u32::MAX), (score.correct_cnt(), score.streak()));
+    }
+
+    #[test]
+    fn question_on_string() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let mut rng = Rng::with_seed(7);
+        let question = FindNoteQuestion::next(&fretboard, &fretboard.points(), false, &PracticeSchedule::default(), 0, &mut rng).unwrap();
+        let FindNoteTarget::OnString(string) = question.target() else { panic!("{question:?}") };
+        assert!(!question.answers.is_empty());
+        assert!(question.answers.iter().all(|answer| *answer.on_string() == string
+            && fretboard.note_of_point(answer).unwrap().note_name() == *question.note_name()));
+    }
+
+    #[test]
+    fn everywhere_quiz_moves_on_once_all_found() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let mut rng = Rng::with_seed(3);
+        let candidates = fretboard.points();
+        let mut schedule = PracticeSchedule::default();
+        let mut quiz = FindNoteQuiz::new(&fretboard, &candidates, true, &schedule, 0, &mut rng).unwrap();
+        let answers = quiz.question().answers.clone();
+        let wrong_point = *candidates.iter().find(|point| !answers.contains(point)).unwrap();
+
+        assert!(matches!(quiz.answer(&fretboard, &candidates, wrong_point, &mut schedule, TIMING, &mut rng), FindNoteFeedback::Incorrect { .. }));
+        assert_eq!(FindNoteFeedback::Correct, quiz.answer(&fretboard, &candidates, answers[0], &mut schedule, TIMING, &mut rng));
+        assert_eq!(FindNoteFeedback::AlreadyFound, quiz.answer(&fretboard, &candidates, answers[0], &mut schedule, TIMING, &mut rng));
+        for answer in &answers[1..] {
+            quiz.answer(&fretboard, &candidates, *answer, &mut schedule, TIMING, &mut rng);
+        }
+        assert!(quiz.question().found().is_empty());
+        assert_eq!(answers.len() as u32, quiz.score().streak());
+        assert_eq!(1, schedule.record_of_point(&fretboard, &answers[0]).unwrap().correct_cnt());
+    }
+
+    #[test]
+    fn clicks_off_the_candidates_are_not_scored() {
+        let fretboard = Fretboard::of_fret_cnt(13).with_capo(Some(Capo::full(2).unwrap())).unwrap();
+        let mut rng = Rng::with_seed(3);
+        let candidates = fretboard.points_in(&Region::of_frets(2..=6));
+        let mut schedule = PracticeSchedule::default();
+        let mut quiz = FindNoteQuiz::new(&fretboard, &candidates, true, &schedule, 0, &mut rng).unwrap();
+        let note_name = quiz.question().note_name().clone();
+        let outside_answer = *fretboard.positions_of_pitch_class(&note_name).iter()
+            .find(|point| !candidates.contains(point))
+            .unwrap();
+
+        assert_eq!(FindNoteFeedback::Unplayable, quiz.answer(&fretboard, &candidates, Point::of(1, 1).unwrap(), &mut schedule, TIMING, &mut rng));
+        assert_eq!(FindNoteFeedback::OutsideRegion, quiz.answer(&fretboard, &candidates, outside_answer, &mut schedule, TIMING, &mut rng));
+        assert_eq!(None, quiz.score().accuracy());
+        assert_eq!(None, schedule.record_of_pitch_class(&note_name));
+    }
+
+    #[test]
+    fn spellings_accepted() {
+        assert!(AccidentalSpelling::Sharps.accepts(&C.sharp(), &D.flat()));
+        assert!(!AccidentalSpelling::Sharps.accepts(&D.flat(), &D.flat()));
+        assert!(AccidentalSpelling::Flats.accepts(&D.flat(), &C.sharp()));
+        assert!(AccidentalSpelling::Both.accepts(&D.flat(), &C.sharp()));
+        assert!(!AccidentalSpelling::Both.accepts(&F.flat(), &E.natural()));
+        assert!(!AccidentalSpelling::Both.accepts(&D.natural(), &E.natural()));
+        assert_eq!("Bb", AccidentalSpelling::Flats.answer_choices()[10].clone().string_representation());
+    }
+
+    #[test]
+    fn name_note_quiz_moves_to_another_point() {
+        let fretboard = Fretboard::of_standard();
+        let candidates = [Point::of(2, 1).unwrap(), Point::of(1, 0).unwrap()];
+        let mut rng = Rng::with_seed(5);
+        let mut schedule = PracticeSchedule::default();
+        let mut quiz = NameNoteQuiz::new(&fretboard, &candidates, AccidentalSpelling::Both, &schedule, 0, &mut rng).unwrap();
+        let first_point = quiz.point();
+        let answer = fretboard.note_of_point(&first_point).unwrap().note_name();
+        assert!(matches!(quiz.answer(&fretboard, &candidates, answer, &mut schedule, TIMING, &mut rng), Some(NameNoteFeedback::Correct { .. })));
+        assert_ne!(first_point, quiz.point());
+        assert!(matches!(quiz.answer(&fretboard, &candidates, G.sharp(), &mut schedule, TIMING, &mut rng),
+            Some(NameNoteFeedback::Incorrect { .. })));
+        assert_eq!((1, 1), (quiz.score().correct_cnt(), quiz.score().incorrect_cnt()));
+        quiz.set_spelling(AccidentalSpelling::Flats);
+        assert!(matches!(quiz.answer(&fretboard, &candidates, F.sharp(), &mut schedule, TIMING, &mut rng),
+            Some(NameNoteFeedback::Incorrect { .. })));
+        assert_eq!(Some(1), schedule.record_of_point(&fretboard, &first_point).map(|record| record.correct_cnt()));
+    }
+
+    #[test]
+    fn no_question_without_candidates() {
+        assert!(FindNoteQuestion::next(&Fretboard::of_standard(), &[], false, &PracticeSchedule::default(), 0, &mut Rng::with_seed(1)).is_none());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::guitar::error::FretboardError;
+use crate::guitar::fretboard::{Fretboard, Point};
+use crate::music::chord::{self, ChordMatch};
+use crate::music::note::Note;
+use crate::music::scale::{self, ScaleMatch};
+
+fn notes_of_points(fretboard : &Fretboard, points : &[Point]) -> Result<Vec<Note>, FretboardError> {
+    points.iter().map(|point| fretboard.note_of_point(point)).collect()
+}
+
+/// 和弦识别 Names the chord sounded by the points, see `chord::identify_chords`.
+pub fn chords_of_points(fretboard : &Fretboard, points : &[Point]) -> Result<Vec<ChordMatch>, FretboardError> {
+    Ok(chord::identify_chords(&notes_of_points(fretboard, points)?))
+}
+
+/// 音阶识别 Scales containing every note of the points, see `scale::identify_scales`.
+pub fn scales_of_points(fretboard : &Fretboard, points : &[Point]) -> Result<Vec<ScaleMatch>, FretboardError> {
+    let note_names : Vec<_> = notes_of_points(fretboard, points)?.iter().map(Note::note_name).collect();
+    Ok(scale::identify_scales(&note_names))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(on_string : u8, behind_fret : u8) -> Point {
+        Point::of(on_string, behind_fret).unwrap()
+    }
+
+    #[test]
+    fn open_c_chord() {
+        // x32010
+        let points = [point(5, 3), point(4, 2), point(3, 0), point(2, 1), point(1, 0)];
+        let best = &chords_of_points(&Fretboard::of_standard(), &points).unwrap()[0];
+        assert_eq!("C", best.string_representation());
+    }
+
+    #[test]
+    fn open_am7_chord() {
+        // x02010
+        let points = [point(5, 0), point(4, 2), point(3, 0), point(2, 1), point(1, 0)];
+        let names : Vec<String> = chords_of_points(&Fretboard::of_standard(), &points).unwrap()
+            .iter().map(ChordMatch::string_representation).collect();
+        assert_eq!(vec!["Am7", "C6/A"], names);
+    }
+
+    #[test]
+    fn missing_string() {
+        assert_eq!(Err(FretboardError::StringNotFound(7)), chords_of_points(&Fretboard::of_standard(), &[point(7, 0)]));
+    }
+}
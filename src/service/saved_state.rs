@@ -0,0 +1,308 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::guitar::region::Region;
+use super::note_label::LabelMode;
+use super::quiz::{AccidentalSpelling, QuizRoundRecord};
+use super::spaced_repetition::PracticeSchedule;
+
+/// Version of the saved state written by this build, to be raised whenever a change needs a migration.
+/// Version 1 only saved the practice schedule, under its own key.
+/// Version 2 kept strings and frets for the "Name the note" questions only, the region of every view since version 3.
+/// Version 3 kept point records by string and fret only, dropped on load as the note they sounded is unknown.
+pub const SAVED_STATE_VERSION : u32 = 4;
+pub const SAVED_STATE_KEY : &str = "saved_state";
+/// Key of the practice schedule saved by version 1
+pub const PRACTICE_SCHEDULE_KEY_V1 : &str = "practice_schedule";
+
+/// 存档错误 Errors of loading the saved state
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum SavedStateError {
+    Parse(String),
+    /// Saved by a newer build; left untouched rather than overwritten.
+    NewerVersion(u32),
+}
+
+impl fmt::Display for SavedStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SavedStateError::Parse(message) => write!(f, "saved state can not be read: {message}"),
+            SavedStateError::NewerVersion(version) => write!(f, "saved state version {version} is newer than {SAVED_STATE_VERSION}, the one supported"),
+        }
+    }
+}
+
+impl std::error::Error for SavedStateError {}
+
+/// 找音测验设置 "Find the note" settings
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct FindNoteSettings {
+    pub is_everywhere : bool,
+    pub round_secs : u64,
+}
+
+impl Default for FindNoteSettings {
+    fn default() -> Self {
+        FindNoteSettings { is_everywhere: false, round_secs: 60 }
+    }
+}
+
+/// 认音测验设置 "Name the note" settings
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct NameNoteSettings {
+    pub spelling : AccidentalSpelling,
+}
+
+impl Default for NameNoteSettings {
+    fn default() -> Self {
+        NameNoteSettings { spelling: AccidentalSpelling::Both }
+    }
+}
+
+/// 设置 User settings. Fields missing from an older save take their default.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub instrument_name : String,
+    pub tuning_name : String,
+    /// From the lowest pitched string up, e.g. "E2 A2 D3 G3 B3 E4"
+    pub tuning_notes : String,
+    pub fret_cnt : u8,
+    /// 0 for no capo
+    pub capo_fret : u8,
+    pub capo_skipped_strings : Vec<u8>,
+    /// Root of the selected scale, e.g. "Eb"
+    pub scale_root : String,
+    /// Name of the selected scale in the catalog
+    pub scale_name : String,
+    pub show_scale_map : bool,
+    /// Frets and strings the scale map, the positions and the quizzes keep to
+    pub region : Region,
+    pub label_mode : LabelMode,
+    pub find_note : FindNoteSettings,
+    pub name_note : NameNoteSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            instrument_name: "Guitar".to_owned(),
+            tuning_name: "Standard".to_owned(),
+            tuning_notes: "E2 A2 D3 G3 B3 E4".to_owned(),
+            fret_cnt: 14,
+            capo_fret: 0,
+            capo_skipped_strings: Vec::new(),
+            scale_root: "C".to_owned(),
+            scale_name: "Major".to_owned(),
+            show_scale_map: false,
+            region: Region::whole(),
+            label_mode: LabelMode::default(),
+            find_note: FindNoteSettings::default(),
+            name_note: NameNoteSettings::default(),
+        }
+    }
+}
+
+/// 学习进度 What the quizzes learnt about the user
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    pub practice_schedule : PracticeSchedule,
+    /// Finished rounds, oldest first
+    pub quiz_history : Vec<QuizRoundRecord>,
+}
+
+/// 存档 Everything kept between runs
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct SavedState {
+    version : u32,
+    #[serde(default)]
+    pub settings : Settings,
+    #[serde(default)]
+    pub progress : Progress,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        SavedState { version: SAVED_STATE_VERSION, settings: Settings::default(), progress: Progress::default() }
+    }
+}
+
+/// Only the version, read first to know how to read the rest
+#[derive(Deserialize)]
+struct VersionHeader {
+    version : u32,
+}
+
+/// Strings and frets of the "Name the note" questions in version 2
+#[derive(PartialEq)]
+#[derive(Deserialize)]
+#[serde(default)]
+struct NameNoteFilterV2 {
+    skipped_strings : Vec<u8>,
+    lowest_fret : u8,
+    highest_fret : u8,
+}
+
+impl Default for NameNoteFilterV2 {
+    fn default() -> Self {
+        NameNoteFilterV2 { skipped_strings: Vec::new(), lowest_fret: 0, highest_fret: 12 }
+    }
+}
+
+/// What version 2 has and later versions don't
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default)]
+struct SavedStateV2 {
+    settings : SettingsV2,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default)]
+struct SettingsV2 {
+    name_note : NameNoteFilterV2,
+}
+
+impl SavedState {
+    pub fn of(settings : Settings, progress : Progress) -> SavedState {
+        SavedState { version: SAVED_STATE_VERSION, settings, progress }
+    }
+
+    /// Reads the saved state from what the storage holds under `SAVED_STATE_KEY` and `PRACTICE_SCHEDULE_KEY_V1`,
+    /// migrating older versions. Nothing saved yet gives the defaults.
+    pub fn load(saved_state : Option<&str>, practice_schedule_v1 : Option<&str>) -> Result<SavedState, SavedStateError> {
+        let parse_error = |err : ron::error::SpannedError| SavedStateError::Parse(err.to_string());
+        let Some(saved_state) = saved_state else {
+            return match practice_schedule_v1 {
+                None => Ok(SavedState::default()),
+                Some(practice_schedule) => Ok(SavedState::migrate_from_v1(ron::from_str(practice_schedule).map_err(parse_error)?)),
+            };
+        };
+
+        let version = ron::from_str::<VersionHeader>(saved_state).map_err(parse_error)?.version;
+        if version > SAVED_STATE_VERSION {
+            return Err(SavedStateError::NewerVersion(version));
+        }
+        // Versions from 2 on only added fields with defaults, apart from the region and the renamed point records
+        let mut loaded = SavedState { version: SAVED_STATE_VERSION, ..ron::from_str(saved_state).map_err(parse_error)? };
+        if version == 2 {
+            let name_note_filter = ron::from_str::<SavedStateV2>(saved_state).map_err(parse_error)?.settings.name_note;
+            loaded.settings.region = SavedState::migrate_region_from_v2(name_note_filter);
+        }
+        Ok(loaded)
+    }
+
+    fn migrate_from_v1(practice_schedule : PracticeSchedule) -> SavedState {
+        SavedState {
+            progress: Progress { practice_schedule, ..Progress::default() },
+            ..SavedState::default()
+        }
+    }
+
+    /// The filter of the quiz becomes the region of every view, unless left as it was by default
+    fn migrate_region_from_v2(name_note_filter : NameNoteFilterV2) -> Region {
+        if name_note_filter == NameNoteFilterV2::default() {
+            return Region::whole();
+        }
+        Region::of(name_note_filter.lowest_fret..=name_note_filter.highest_fret, name_note_filter.skipped_strings)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::to_string(self).expect("saved state is serializable")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::guitar::fretboard::{Fretboard, Point};
+    use crate::music::note::NaturalNoteName::*;
+    use crate::service::quiz::{QuizMode, QuizScore};
+    use crate::service::spaced_repetition::AnswerTiming;
+    use super::*;
+
+    fn progress() -> Progress {
+        let mut practice_schedule = PracticeSchedule::default();
+        practice_schedule.record(&Fretboard::of_standard(), Point::of(2, 1).unwrap(), &C.natural(), true,
+            AnswerTiming { response_time: Duration::from_secs(1), answered_at: 100 });
+        let quiz_history = vec![QuizRoundRecord { mode: QuizMode::FindNote, finished_at: 200, score: QuizScore::default() }];
+        Progress { practice_schedule, quiz_history }
+    }
+
+    #[test]
+    fn nothing_saved() {
+        assert_eq!(Ok(SavedState::default()), SavedState::load(None, None));
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings { fret_cnt: 22, capo_fret: 2, region: Region::of(5..=9, vec![4, 5, 6]), ..Settings::default() };
+        let saved_state = SavedState::of(settings, progress());
+        assert_eq!(Ok(saved_state.clone()), SavedState::load(Some(&saved_state.to_ron()), None));
+    }
+
+    #[test]
+    fn migrate_practice_schedule_of_v1() {
+        let practice_schedule = progress().practice_schedule;
+        let saved_state = SavedState::load(None, Some(&ron::to_string(&practice_schedule).unwrap())).unwrap();
+        assert_eq!(practice_schedule, saved_state.progress.practice_schedule);
+        assert_eq!(Settings::default(), saved_state.settings);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let saved_state = SavedState::load(Some("(version: 2, settings: (fret_cnt: 20))"), None).unwrap();
+        assert_eq!(20, saved_state.settings.fret_cnt);
+        assert_eq!("Guitar", saved_state.settings.instrument_name);
+    }
+
+    #[test]
+    fn migrate_name_note_filter_of_v2() {
+        let saved_state = SavedState::load(Some("(version: 2, settings: (name_note: (spelling: Flats, skipped_strings: [6], lowest_fret: 3, highest_fret: 7)))"), None).unwrap();
+        assert_eq!(Region::of(3..=7, vec![6]), saved_state.settings.region);
+        assert_eq!(AccidentalSpelling::Flats, saved_state.settings.name_note.spelling);
+        let saved_state = SavedState::load(Some("(version: 2, settings: (name_note: (lowest_fret: 0, highest_fret: 12)))"), None).unwrap();
+        assert_eq!(Region::whole(), saved_state.settings.region);
+    }
+
+    #[test]
+    fn point_records_of_v3_are_dropped() {
+        let saved_state = SavedState::load(Some("(version: 3, progress: (practice_schedule: (\
+            points: {(on_string: 2, behind_fret: 1): (leitner_box: 1, correct_cnt: 1, incorrect_cnt: 0, total_response_millis: 1000, due_at: 160)},\
+            pitch_classes: {0: (leitner_box: 1, correct_cnt: 1, incorrect_cnt: 0, total_response_millis: 1000, due_at: 160)})))"), None).unwrap();
+        let practice_schedule = &saved_state.progress.practice_schedule;
+        assert_eq!(None, practice_schedule.record_of_point(&Fretboard::of_standard(), &Point::of(2, 1).unwrap()));
+        assert_eq!(Some(1), practice_schedule.record_of_pitch_class(&C.natural()).map(|record| record.correct_cnt()));
+    }
+
+    #[test]
+    fn newer_version_is_not_read() {
+        assert_eq!(Err(SavedStateError::NewerVersion(5)), SavedState::load(Some("(version: 5)"), None));
+        assert!(matches!(SavedState::load(Some("not ron"), None), Err(SavedStateError::Parse(_))));
+    }
+}
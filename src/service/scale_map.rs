@@ -1,21 +1,26 @@
-use crate::{guitar::fretboard::{Fretboard, Point}, music::note::NoteName};
+use crate::{guitar::fretboard::{Fretboard, Point}, guitar::region::Region, music::note::NoteName};
 
-pub fn scale_notes_on_fretboard<const N: usize>(fretboard : &Fretboard, scale : [NoteName; N]) -> Vec<Point> {
+pub fn scale_notes_on_fretboard<S: AsRef<[NoteName]>>(fretboard : &Fretboard, scale : S) -> Vec<Point> {
+    let scale = scale.as_ref();
     let mut scale_notes = Vec::new();
-    for string_name in fretboard.string_name_vec() {
-        for fret_bar in 0..fretboard.fret_bar_cnt() {
-            let cur_point = Point::of(string_name, fret_bar);
-            let cur_note = fretboard.note_of_point(&cur_point);
-            if scale.contains(&cur_note.note_name()) {
-                scale_notes.push(cur_point);
-            }
+    for cur_point in fretboard.points() {
+        if let Ok(cur_note) = fretboard.note_of_point(&cur_point)
+            && scale.contains(&cur_note.note_name()) {
+            scale_notes.push(cur_point);
         }
     }
     scale_notes
 }
 
+/// 区域 The scale's points in the region, e.g. between two frets, open strings counting as their nut or capo fret.
+pub fn scale_notes_in_region<S: AsRef<[NoteName]>>(fretboard : &Fretboard, scale : S, region : &Region) -> Vec<Point> {
+    scale_notes_on_fretboard(fretboard, scale).into_iter()
+        .filter(|point| region.contains(fretboard, point))
+        .collect()
+}
+
 #[cfg(test)]
-mod scale_map {
+mod tests {
     use std::hash::Hash;
     use std::collections::HashSet;
 
@@ -27,12 +32,12 @@ mod scale_map {
     fn major_c_on_0_to_4() {
         assert!(is_unordered_equal(
             &[
-                Point::of(1, 0), Point::of(1, 1),                  Point::of(1, 3),
-                Point::of(2, 0), Point::of(2, 1),                  Point::of(2, 3),
-                Point::of(3, 0),                  Point::of(3, 2),
-                Point::of(4, 0),                  Point::of(4, 2), Point::of(4, 3),
-                Point::of(5, 0),                  Point::of(5, 2), Point::of(5, 3),
-                Point::of(6, 0), Point::of(6, 1),                  Point::of(6, 3),
+                point(1, 0), point(1, 1),                  point(1, 3),
+                point(2, 0), point(2, 1),                  point(2, 3),
+                point(3, 0),                  point(3, 2),
+                point(4, 0),                  point(4, 2), point(4, 3),
+                point(5, 0),                  point(5, 2), point(5, 3),
+                point(6, 0), point(6, 1),                  point(6, 3),
             ],
             &scale_notes_on_fretboard(
                 &Fretboard::of_fret_cnt(4),
@@ -40,6 +45,44 @@ mod scale_map {
         ));
     }
 
+    #[test]
+    fn whole_tone_c_on_first_string() {
+        let whole_tone = scale::Scale::of_formula(C.natural(), "1 2 3 #4 #5 b7").unwrap();
+        let first_string_points : Vec<Point> = scale_notes_on_fretboard(&Fretboard::of_fret_cnt(5), whole_tone.note_names())
+            .into_iter()
+            .filter(|point| *point.on_string() == 1)
+            .collect();
+        assert!(is_unordered_equal(&[point(1, 0), point(1, 2), point(1, 4)], &first_string_points));
+    }
+
+    #[test]
+    fn major_g_in_second_position_under_capo() {
+        let fretboard = Fretboard::of_fret_cnt(13).with_capo(Some(crate::guitar::capo::Capo::full(2).unwrap())).unwrap();
+        let points = scale_notes_in_region(&fretboard, scale::major_scale_of(G.natural()), &Region::of_frets(2..=3));
+        // Open strings sound at the capo
+        assert!(is_unordered_equal(
+            &[point(1, 0), point(1, 3), point(2, 3), point(3, 0), point(4, 0), point(5, 0), point(5, 3), point(6, 0), point(6, 3)],
+            &points
+        ));
+    }
+
+    #[test]
+    fn major_c_on_first_three_strings_from_fret_5_to_9() {
+        let points = scale_notes_in_region(&Fretboard::of_standard(), scale::major_scale_of(C.natural()), &Region::of(5..=9, vec![4, 5, 6]));
+        assert!(is_unordered_equal(
+            &[
+                point(1, 5), point(1, 7), point(1, 8),
+                point(2, 5), point(2, 6), point(2, 8),
+                point(3, 5), point(3, 7), point(3, 9),
+            ],
+            &points
+        ));
+    }
+
+    fn point(on_string : u8, behind_fret : u8) -> Point {
+        Point::of(on_string, behind_fret).unwrap()
+    }
+
     fn is_unordered_equal<T>(a: &[T], b: &[T]) -> bool
     where
         T: Eq + Hash,
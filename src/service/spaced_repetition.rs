@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::guitar::fretboard::{Fretboard, Point};
+use crate::music::note::NoteName;
+
+/// Seconds to wait before reviewing again, by Leitner box: right away, a minute, ten minutes, a day, three days, a week, two weeks.
+const BOX_REVIEW_INTERVALS_SECS : [u64; 7] = [0, 60, 10 * 60, 24 * 3600, 3 * 24 * 3600, 7 * 24 * 3600, 14 * 24 * 3600];
+const LAST_BOX : u8 = BOX_REVIEW_INTERVALS_SECS.len() as u8 - 1;
+/// Correct answers slower than this are not promoted to the next box.
+const SLOW_ANSWER : Duration = Duration::from_secs(5);
+
+/// 答题时间 When and how fast a question was answered
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct AnswerTiming {
+    pub response_time : Duration,
+    /// Seconds since the Unix epoch
+    pub answered_at : u64,
+}
+
+/// 复习记录 Accuracy, response time and Leitner box of a point or a note
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct ReviewRecord {
+    /// May be above `LAST_BOX` when loaded from a damaged save, read through `leitner_box()`
+    leitner_box : u8,
+    correct_cnt : u32,
+    incorrect_cnt : u32,
+    total_response_millis : u64,
+    /// Seconds since the Unix epoch
+    due_at : u64,
+}
+
+impl ReviewRecord {
+    /// A correct answer moves the record up a box, unless slow; a wrong one sends it back to the first box.
+    fn review(&mut self, is_correct : bool, timing : AnswerTiming) {
+        if is_correct {
+            self.correct_cnt = self.correct_cnt.saturating_add(1);
+            if timing.response_time <= SLOW_ANSWER {
+                self.leitner_box = (self.leitner_box() + 1).min(LAST_BOX);
+            }
+        } else {
+            self.incorrect_cnt = self.incorrect_cnt.saturating_add(1);
+            self.leitner_box = 0;
+        }
+        self.total_response_millis = self.total_response_millis.saturating_add(timing.response_time.as_millis() as u64);
+        self.due_at = timing.answered_at.saturating_add(BOX_REVIEW_INTERVALS_SECS[self.leitner_box() as usize]);
+    }
+
+    /// 0 for the weakest, reviewed most often, up to `LAST_BOX`
+    pub fn leitner_box(&self) -> u8 {
+        self.leitner_box.min(LAST_BOX)
+    }
+
+    pub fn correct_cnt(&self) -> u32 {
+        self.correct_cnt
+    }
+
+    pub fn incorrect_cnt(&self) -> u32 {
+        self.incorrect_cnt
+    }
+
+    /// Counted in u64, the counts of a damaged save may add up beyond a u32
+    fn answer_cnt(&self) -> u64 {
+        self.correct_cnt as u64 + self.incorrect_cnt as u64
+    }
+
+    /// Ratio of correct answers, None before the first answer.
+    pub fn accuracy(&self) -> Option<f32> {
+        let answer_cnt = self.answer_cnt();
+        (answer_cnt != 0).then(|| self.correct_cnt as f32 / answer_cnt as f32)
+    }
+
+    pub fn average_response_time(&self) -> Option<Duration> {
+        let answer_cnt = self.answer_cnt();
+        (answer_cnt != 0).then(|| Duration::from_millis(self.total_response_millis / answer_cnt))
+    }
+
+    pub fn is_due(&self, now : u64) -> bool {
+        self.due_at <= now
+    }
+}
+
+/// 发音位置 A point with the pitch it sounds, so that its record is left behind
+/// once another instrument, tuning or capo makes the point sound another note
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq, Hash)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+struct SoundingPoint {
+    point : Point,
+    /// Semitones above C0
+    pitch : i32,
+}
+
+impl SoundingPoint {
+    /// None if the point is not played on the fretboard
+    fn of(fretboard : &Fretboard, point : Point) -> Option<SoundingPoint> {
+        let pitch = fretboard.note_of_point(&point).ok()?.semitones_from_octave_zero();
+        Some(SoundingPoint { point, pitch })
+    }
+}
+
+/// 间隔重复 Leitner-box schedule of the fretboard points and the notes (pitch classes), weak ones asked more often.
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct PracticeSchedule {
+    /// Named apart from the `points` of version 3, keyed by string and fret only and dropped on load
+    #[serde(default)]
+    sounding_points : HashMap<SoundingPoint, ReviewRecord>,
+    /// Keyed by integer notation
+    pitch_classes : HashMap<u8, ReviewRecord>,
+}
+
+impl PracticeSchedule {
+    /// Records an answer about the note on a point, the point's record kept for the note it sounds on this fretboard.
+    pub fn record(&mut self, fretboard : &Fretboard, point : Point, note_name : &NoteName, is_correct : bool, timing : AnswerTiming) {
+        if let Some(sounding_point) = SoundingPoint::of(fretboard, point) {
+            self.sounding_points.entry(sounding_point).or_default().review(is_correct, timing);
+        }
+        self.record_pitch_class(note_name, is_correct, timing);
+    }
+
+    /// Records an answer about a note, on no point in particular, e.g. a wrong click when looking for it.
+    pub fn record_pitch_class(&mut self, note_name : &NoteName, is_correct : bool, timing : AnswerTiming) {
+        self.pitch_classes.entry(note_name.integer_notation()).or_default().review(is_correct, timing);
+    }
+
+    /// The record of the point while it sounds the same note as on this fretboard
+    pub fn record_of_point(&self, fretboard : &Fretboard, point : &Point) -> Option<&ReviewRecord> {
+        self.sounding_points.get(&SoundingPoint::of(fretboard, *point)?)
+    }
+
+    pub fn record_of_pitch_class(&self, note_name : &NoteName) -> Option<&ReviewRecord> {
+        self.pitch_classes.get(&note_name.integer_notation())
+    }
+
+    /// Picks a due candidate at random, the lower the boxes of the point and of its note the likelier.
+    /// Never reviewed points are due. Without due candidates, the one due the soonest is picked.
+    pub fn next_point(&self, fretboard : &Fretboard, candidates : &[Point], now : u64, rng : &mut Rng) -> Option<Point> {
+        let default_record = ReviewRecord::default();
+        let weighed_candidates : Vec<(Point, &ReviewRecord, u64)> = candidates.iter()
+            .map(|point| {
+                let point_record = self.record_of_point(fretboard, point).unwrap_or(&default_record);
+                let note_box = fretboard.note_of_point(point).ok()
+                    .and_then(|note| self.record_of_pitch_class(&note.note_name()))
+                    .map_or(0, ReviewRecord::leitner_box);
+                let weight = (LAST_BOX + 1).saturating_sub(point_record.leitner_box()) as u64 * (LAST_BOX + 1).saturating_sub(note_box) as u64;
+                (*point, point_record, weight)
+            })
+            .collect();
+
+        let due_weight_sum : u64 = weighed_candidates.iter()
+            .filter(|(_, record, _)| record.is_due(now))
+            .map(|(_, _, weight)| weight)
+            .sum();
+        if due_weight_sum == 0 {
+            return weighed_candidates.iter()
+                .min_by_key(|(_, record, _)| record.due_at)
+                .map(|(point, _, _)| *point);
+        }
+
+        let mut pick = rng.u64(..due_weight_sum);
+        for (point, record, weight) in &weighed_candidates {
+            if !record.is_due(now) {
+                continue;
+            }
+            if pick < *weight {
+                return Some(*point);
+            }
+            pick -= weight;
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::guitar::capo::Capo;
+    use crate::guitar::tuning::Tuning;
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn timing(response_secs : u64, answered_at : u64) -> AnswerTiming {
+        AnswerTiming { response_time: Duration::from_secs(response_secs), answered_at }
+    }
+
+    #[test]
+    fn leitner_boxes() {
+        let mut record = ReviewRecord::default();
+        record.review(true, timing(1, 0));
+        record.review(true, timing(1, 100));
+        assert_eq!(2, record.leitner_box());
+        assert!(!record.is_due(100 + 599));
+        assert!(record.is_due(100 + 600));
+        // Slow, not promoted
+        record.review(true, timing(9, 1000));
+        assert_eq!(2, record.leitner_box());
+        record.review(false, timing(1, 2000));
+        assert_eq!(0, record.leitner_box());
+        assert!(record.is_due(2000));
+        assert_eq!(Some(0.75), record.accuracy());
+        assert_eq!(Some(Duration::from_secs(3)), record.average_response_time());
+    }
+
+    #[test]
+    fn boxes_beyond_the_last_one_are_clamped() {
+        let mut record : ReviewRecord = ron::from_str(
+            "(leitner_box: 200, correct_cnt: 1, incorrect_cnt: 0, total_response_millis: 1000, due_at: 0)").unwrap();
+        assert_eq!(LAST_BOX, record.leitner_box());
+        record.review(true, timing(1, 0));
+        assert_eq!(LAST_BOX, record.leitner_box());
+
+        let mut schedule = PracticeSchedule::default();
+        let fretboard = Fretboard::of_standard();
+        let point = Point::of(1, 0).unwrap();
+        schedule.sounding_points.insert(SoundingPoint::of(&fretboard, point).unwrap(), ReviewRecord { leitner_box: 200, ..ReviewRecord::default() });
+        assert_eq!(Some(point), schedule.next_point(&fretboard, &[point], 0, &mut Rng::with_seed(1)));
+    }
+
+    #[test]
+    fn counts_beyond_a_u32_do_not_overflow() {
+        let mut record = ReviewRecord { correct_cnt: u32::MAX, incorrect_cnt: 1, ..ReviewRecord::default() };
+        assert!(record.accuracy().unwrap() > 0.99);
+        assert!(record.average_response_time().is_some());
+        record.review(true, timing(1, 0));
+        assert_eq!(u32::MAX, record.correct_cnt());
+    }
+
+    #[test]
+    fn learned_points_wait() {
+        let fretboard = Fretboard::of_standard();
+        let (learned, weak) = (Point::of(1, 0).unwrap(), Point::of(2, 1).unwrap());
+        let mut schedule = PracticeSchedule::default();
+        schedule.record(&fretboard, learned, &E.natural(), true, timing(1, 0));
+        schedule.record(&fretboard, weak, &C.natural(), false, timing(1, 0));
+        let mut rng = Rng::with_seed(11);
+        for _ in 0..20 {
+            assert_eq!(Some(weak), schedule.next_point(&fretboard, &[learned, weak], 10, &mut rng));
+        }
+        assert_eq!(Some(learned), schedule.next_point(&fretboard, &[learned], 10, &mut rng));
+        assert_eq!(None, schedule.next_point(&fretboard, &[], 10, &mut rng));
+    }
+
+    #[test]
+    fn point_records_follow_the_sounding_note() {
+        let fretboard = Fretboard::of_standard();
+        let point = Point::of(6, 0).unwrap();
+        let mut schedule = PracticeSchedule::default();
+        schedule.record(&fretboard, point, &E.natural(), true, timing(1, 0));
+        assert!(schedule.record_of_point(&fretboard, &point).is_some());
+        let drop_d = fretboard.with_tuning(Tuning::preset("Drop D").unwrap());
+        assert_eq!(None, schedule.record_of_point(&drop_d, &point));
+        // The open string sounds above the capo
+        let capo = fretboard.with_capo(Some(Capo::full(1).unwrap())).unwrap();
+        assert_eq!(None, schedule.record_of_point(&capo, &point));
+        // Same note and string elsewhere on the neck is another point
+        assert_eq!(None, schedule.record_of_point(&drop_d, &Point::of(6, 2).unwrap()));
+    }
+
+    #[test]
+    fn pitch_class_records_ignore_spelling() {
+        let mut schedule = PracticeSchedule::default();
+        schedule.record_pitch_class(&C.sharp(), false, timing(2, 0));
+        assert_eq!(1, schedule.record_of_pitch_class(&D.flat()).unwrap().incorrect_cnt());
+    }
+}
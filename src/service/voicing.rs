@@ -0,0 +1,447 @@
+use crate::guitar::error::FretboardError;
+use crate::guitar::fretboard::{Fretboard, Point, StringName};
+use crate::music::chord::Chord;
+use crate::music::note::{Interval, Note};
+
+/// 指法限制 What a voicing may ask of the fretting hand and which chord tones it may leave out
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct VoicingConstraints {
+    /// Frets the hand covers, both ends counted, e.g. 3 for x32010. Open strings are not counted.
+    pub max_fret_span : u8,
+    /// Fingers pressing strings, a barre across the lowest fretted fret counted once
+    pub max_finger_cnt : u8,
+    /// Chord tones that may be left out, every other one is required, e.g. the fifth of a seventh chord
+    pub optional_tones : Vec<Interval>,
+}
+
+impl Default for VoicingConstraints {
+    fn default() -> Self {
+        VoicingConstraints { max_fret_span: 4, max_finger_cnt: 4, optional_tones: vec![Interval::PERFECT_FIFTH] }
+    }
+}
+
+/// 横按 The index finger pressing several strings on one fret
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct Barre {
+    fret : u8,
+    /// The highest pitched string barred, i.e. with the smallest number
+    first_string : StringName,
+    last_string : StringName,
+}
+
+impl Barre {
+    pub fn fret(&self) -> u8 {
+        self.fret
+    }
+
+    pub fn first_string(&self) -> StringName {
+        self.first_string
+    }
+
+    pub fn last_string(&self) -> StringName {
+        self.last_string
+    }
+}
+
+/// 和弦指法 One way to play a chord, each string muted, open or fretted
+#[derive(Clone)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct Voicing {
+    /// Indexed by string, string 1 first; None for a muted string
+    string_points : Vec<Option<Point>>,
+    /// Suggested finger of each string, 1 for the index; None for a muted or open string
+    fingers : Vec<Option<u8>>,
+    barre : Option<Barre>,
+    finger_cnt : u8,
+    fret_span : u8,
+    is_root_in_bass : bool,
+    omitted_tone_cnt : u8,
+}
+
+impl Voicing {
+    /// The point played on the string, None if muted or not on the fretboard.
+    pub fn point_on_string(&self, string : StringName) -> Option<Point> {
+        (string as usize).checked_sub(1)
+            .and_then(|string_index| self.string_points.get(string_index))
+            .copied()
+            .flatten()
+    }
+
+    /// Sounding points, string 1 first
+    pub fn points(&self) -> Vec<Point> {
+        self.string_points.iter().flatten().copied().collect()
+    }
+
+    /// The fret played on each string, string 1 first; None for a muted string
+    pub fn frets(&self) -> Vec<Option<u8>> {
+        self.string_points.iter().map(|point| point.map(|point| *point.behind_fret())).collect()
+    }
+
+    /// Suggested finger of each string, string 1 first, 1 for the index; None for a muted or open string
+    pub fn fingers(&self) -> &[Option<u8>] {
+        &self.fingers
+    }
+
+    pub fn barre(&self) -> Option<Barre> {
+        self.barre
+    }
+
+    pub fn finger_cnt(&self) -> u8 {
+        self.finger_cnt
+    }
+
+    pub fn fret_span(&self) -> u8 {
+        self.fret_span
+    }
+
+    pub fn is_root_in_bass(&self) -> bool {
+        self.is_root_in_bass
+    }
+
+    pub fn omitted_tone_cnt(&self) -> u8 {
+        self.omitted_tone_cnt
+    }
+
+    /// Muted strings between sounding ones, hard to keep quiet when strumming
+    pub fn inner_muted_string_cnt(&self) -> usize {
+        let first_sounding = self.string_points.iter().position(Option::is_some);
+        let last_sounding = self.string_points.iter().rposition(Option::is_some);
+        match (first_sounding, last_sounding) {
+            (Some(first), Some(last)) => self.string_points[first..=last].iter().filter(|point| point.is_none()).count(),
+            _ => 0,
+        }
+    }
+
+    pub fn muted_string_cnt(&self) -> usize {
+        self.string_points.iter().filter(|point| point.is_none()).count()
+    }
+
+    /// 难度 The lower the easier: two per finger, one per fret of span and per muted string, one per two frets up the neck.
+    pub fn effort(&self) -> usize {
+        let lowest_fretted_fret = self.points().iter()
+            .map(|point| *point.behind_fret())
+            .filter(|fret| *fret != 0)
+            .min()
+            .unwrap_or(0);
+        2 * self.finger_cnt as usize + self.fret_span as usize + self.muted_string_cnt() + lowest_fretted_fret as usize / 2
+    }
+
+    /// Chord chart notation from the lowest string, e.g. "x32010"; frets are separated by dashes when one of them has two digits.
+    pub fn string_representation(&self) -> String {
+        let frets : Vec<String> = self.string_points.iter().rev()
+            .map(|point| point.map_or("x".to_owned(), |point| point.behind_fret().to_string()))
+            .collect();
+        if frets.iter().any(|fret| fret.len() > 1) {
+            frets.join("-")
+        } else {
+            frets.concat()
+        }
+    }
+}
+
+/// 和弦指法生成 Every voicing of the chord within the constraints, the most playable first:
+/// no muted string between sounding ones, root in the bass, every chord tone present,
+/// then the least effort for the hand, see `Voicing::effort`, and fewer muted strings.
+pub fn voicings_of_chord(fretboard : &Fretboard, chord : &Chord, constraints : &VoicingConstraints) -> Result<Vec<Voicing>, FretboardError> {
+    let chord_tones : Vec<u8> = chord.note_names().iter().map(|note_name| note_name.integer_notation()).collect();
+    let required_tones : Vec<u8> = chord.quality().intervals().iter()
+        .zip(&chord_tones)
+        .filter(|(interval, _)| !constraints.optional_tones.contains(interval))
+        .map(|(_, pitch_class)| *pitch_class)
+        .collect();
+
+    // Chord tone points of each string, string 1 first
+    let mut string_candidates : Vec<Vec<(Point, Note)>> = Vec::new();
+    for string in fretboard.string_name_vec() {
+        let mut candidates = Vec::new();
+        for behind_fret in 0..fretboard.fret_bar_cnt() {
+            let point = Point::of(string, behind_fret)?;
+            if fretboard.contains(&point)
+                && let Ok(note) = fretboard.note_of_point(&point)
+                && chord_tones.contains(&note.note_name().integer_notation()) {
+                candidates.push((point, note));
+            }
+        }
+        string_candidates.push(candidates);
+    }
+
+    let mut reachable_tones = vec![0; string_candidates.len() + 1];
+    for (string_index, candidates) in string_candidates.iter().enumerate().rev() {
+        reachable_tones[string_index] = candidates.iter()
+            .fold(reachable_tones[string_index + 1], |tones, (_, note)| tones | pitch_class_bit(note));
+    }
+    let search = VoicingSearch {
+        string_candidates: &string_candidates,
+        reachable_tones: &reachable_tones,
+        required_tones: required_tones.iter().fold(0, |tones, pitch_class| tones | 1 << pitch_class),
+        constraints,
+    };
+
+    let mut voicings = Vec::new();
+    let mut string_points = Vec::with_capacity(string_candidates.len());
+    search.collect_voicings(&mut string_points, PartialVoicing::default(), &mut |string_points| {
+        if let Some(voicing) = voicing_of(chord, &chord_tones, &required_tones, constraints, string_points) {
+            voicings.push(voicing);
+        }
+    });
+
+    voicings.sort_by_cached_key(|voicing| (
+        voicing.inner_muted_string_cnt(),
+        !voicing.is_root_in_bass,
+        voicing.omitted_tone_cnt,
+        voicing.effort(),
+        voicing.muted_string_cnt(),
+    ));
+    Ok(voicings)
+}
+
+fn pitch_class_bit(note : &Note) -> u16 {
+    1 << note.note_name().integer_notation()
+}
+
+/// 剪枝搜索 Chooses one candidate or none per string, string 1 first,
+/// dropping a partial choice as soon as no choice of the strings left can make it a voicing
+struct VoicingSearch<'a> {
+    /// Chord tone points of each string, string 1 first
+    string_candidates : &'a [Vec<(Point, Note)>],
+    /// Chord tones on each string or the strings after it, one bit per pitch class
+    reachable_tones : &'a [u16],
+    required_tones : u16,
+    constraints : &'a VoicingConstraints,
+}
+
+/// What the strings chosen so far ask of the hand and which tones they sound
+#[derive(Clone, Copy)]
+#[derive(Default)]
+struct PartialVoicing {
+    /// Lowest and highest fretted frets, None while every chosen string is muted or open
+    fretted_range : Option<(u8, u8)>,
+    fretted_cnt : usize,
+    lowest_fret_cnt : usize,
+    sounding_tones : u16,
+}
+
+impl PartialVoicing {
+    fn with(self, point : &Point, note : &Note) -> PartialVoicing {
+        let fret = *point.behind_fret();
+        let sounding_tones = self.sounding_tones | pitch_class_bit(note);
+        if fret == 0 {
+            return PartialVoicing { sounding_tones, ..self };
+        }
+        let (fretted_range, lowest_fret_cnt) = match self.fretted_range {
+            None => (Some((fret, fret)), 1),
+            Some((lowest, highest)) if fret < lowest => (Some((fret, highest)), 1),
+            Some((lowest, highest)) => (Some((lowest, highest.max(fret))), self.lowest_fret_cnt + usize::from(fret == lowest)),
+        };
+        PartialVoicing { fretted_range, fretted_cnt: self.fretted_cnt + 1, lowest_fret_cnt, sounding_tones }
+    }
+}
+
+impl VoicingSearch<'_> {
+    /// Calls `on_voicing` with every choice that may still fit the hand and have the required tones.
+    fn collect_voicings(&self, string_points : &mut Vec<Option<(Point, Note)>>, partial : PartialVoicing,
+                        on_voicing : &mut impl FnMut(&[Option<(Point, Note)>])) {
+        let Some(candidates) = self.string_candidates.get(string_points.len()) else {
+            on_voicing(string_points);
+            return;
+        };
+        for candidate in std::iter::once(None).chain(candidates.iter().map(Some)) {
+            let next_partial = candidate.map_or(partial, |(point, note)| partial.with(point, note));
+            if self.may_become_voicing(&next_partial, string_points.len() + 1) {
+                string_points.push(candidate.cloned());
+                self.collect_voicings(string_points, next_partial, on_voicing);
+                string_points.pop();
+            }
+        }
+    }
+
+    /// False once the first `chosen_cnt` strings are too wide or need too many fingers,
+    /// or the strings left can no longer supply the missing required tones.
+    /// Both hand measures only grow as strings are added, so no voicing is lost.
+    fn may_become_voicing(&self, partial : &PartialVoicing, chosen_cnt : usize) -> bool {
+        if let Some((lowest, highest)) = partial.fretted_range {
+            // At least one finger on the lowest fret, be it a barre, and one more for every other fretted string
+            let least_finger_cnt = partial.fretted_cnt - partial.lowest_fret_cnt + 1;
+            if highest - lowest + 1 > self.constraints.max_fret_span || least_finger_cnt > self.constraints.max_finger_cnt as usize {
+                return false;
+            }
+        }
+        let missing_tones = self.required_tones & !partial.sounding_tones;
+        let left_string_cnt = self.string_candidates.len() - chosen_cnt;
+        missing_tones & !self.reachable_tones[chosen_cnt] == 0 && missing_tones.count_ones() as usize <= left_string_cnt
+    }
+}
+
+/// The voicing if it has the required tones and fits the hand
+fn voicing_of(chord : &Chord, chord_tones : &[u8], required_tones : &[u8], constraints : &VoicingConstraints,
+              string_points : &[Option<(Point, Note)>]) -> Option<Voicing> {
+    let sounding : Vec<&(Point, Note)> = string_points.iter().flatten().collect();
+    let sounding_tones : Vec<u8> = sounding.iter().map(|(_, note)| note.note_name().integer_notation()).collect();
+    if required_tones.iter().any(|tone| !sounding_tones.contains(tone)) {
+        return None;
+    }
+
+    let fretted_frets : Vec<u8> = sounding.iter()
+        .map(|(point, _)| *point.behind_fret())
+        .filter(|fret| *fret != 0)
+        .collect();
+    let fret_span = match (fretted_frets.iter().min(), fretted_frets.iter().max()) {
+        (Some(lowest), Some(highest)) => highest - lowest + 1,
+        _ => 0,
+    };
+    let points : Vec<Option<Point>> = string_points.iter().map(|point| point.as_ref().map(|(point, _)| *point)).collect();
+    let (fingers, barre) = fingers_of(&points);
+    let finger_cnt = fingers.iter().flatten().copied().max().unwrap_or(0);
+    if fret_span > constraints.max_fret_span || finger_cnt > constraints.max_finger_cnt {
+        return None;
+    }
+
+    let bass = sounding.iter().min_by_key(|(_, note)| note.minus_note(&sounding[0].1).semitone_diff())?;
+    let mut omitted_tones = chord_tones.to_vec();
+    omitted_tones.sort();
+    omitted_tones.dedup();
+    omitted_tones.retain(|tone| !sounding_tones.contains(tone));
+    Some(Voicing {
+        string_points: points,
+        fingers,
+        barre,
+        finger_cnt,
+        fret_span,
+        is_root_in_bass: bass.1.note_name() == chord.root(),
+        omitted_tone_cnt: omitted_tones.len() as u8,
+    })
+}
+
+/// Suggested fingers, 1 for the index, by string, and the barre if any:
+/// the index finger barres the lowest fretted fret when it is on several strings with no open string sounding between them,
+/// other fretted strings take the next fingers from the lowest fret up, from the lowest pitched string on a fret.
+fn fingers_of(string_points : &[Option<Point>]) -> (Vec<Option<u8>>, Option<Barre>) {
+    let frets : Vec<Option<u8>> = string_points.iter().map(|point| point.map(|point| *point.behind_fret())).collect();
+    let mut fingers = vec![None; frets.len()];
+    let Some(lowest_fret) = frets.iter().flatten().copied().filter(|fret| *fret != 0).min() else {
+        return (fingers, None);
+    };
+
+    let lowest_fret_strings : Vec<usize> = frets.iter().enumerate()
+        .filter(|(_, fret)| **fret == Some(lowest_fret))
+        .map(|(string_index, _)| string_index)
+        .collect();
+    let (first_index, last_index) = (lowest_fret_strings[0], lowest_fret_strings[lowest_fret_strings.len() - 1]);
+    let barre = (lowest_fret_strings.len() > 1 && frets[first_index..=last_index].iter().all(|fret| *fret != Some(0)))
+        .then(|| Barre { fret: lowest_fret, first_string: first_index as StringName + 1, last_string: last_index as StringName + 1 });
+    if barre.is_some() {
+        for string_index in &lowest_fret_strings {
+            fingers[*string_index] = Some(1);
+        }
+    }
+
+    let mut unassigned : Vec<(u8, usize)> = frets.iter().enumerate()
+        .filter_map(|(string_index, fret)| fret.map(|fret| (fret, string_index)))
+        .filter(|(fret, string_index)| *fret != 0 && fingers[*string_index].is_none())
+        .collect();
+    unassigned.sort_by_key(|(fret, string_index)| (*fret, std::cmp::Reverse(*string_index)));
+    let first_free_finger = if barre.is_some() { 2 } else { 1 };
+    for (finger, (_, string_index)) in (first_free_finger..).zip(unassigned) {
+        fingers[string_index] = Some(finger);
+    }
+    (fingers, barre)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::guitar::capo::Capo;
+    use crate::guitar::instrument::Instrument;
+    use crate::music::chord::ChordQuality;
+    use crate::music::note::NaturalNoteName::*;
+    use super::*;
+
+    fn representations(voicings : &[Voicing]) -> Vec<String> {
+        voicings.iter().map(Voicing::string_representation).collect()
+    }
+
+    #[test]
+    fn open_chords_come_first() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let c_major = voicings_of_chord(&fretboard, &Chord::of(C.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert_eq!("x32010", c_major[0].string_representation());
+        assert_eq!(3, c_major[0].finger_cnt());
+
+        let e_major = voicings_of_chord(&fretboard, &Chord::of(E.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert_eq!("022100", e_major[0].string_representation());
+    }
+
+    #[test]
+    fn barre_chords() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let f_major = voicings_of_chord(&fretboard, &Chord::of(F.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert_eq!("133211", f_major[0].string_representation());
+        assert_eq!(4, f_major[0].finger_cnt());
+        assert!(representations(&f_major).contains(&"x-8-10-10-10-8".to_owned()));
+    }
+
+    #[test]
+    fn required_and_optional_tones() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let chord = Chord::of(G.natural(), ChordQuality::Dominant7);
+        let voicings = voicings_of_chord(&fretboard, &chord, &VoicingConstraints::default()).unwrap();
+        let chord_tones = chord.note_names();
+        let (third, seventh) = (&chord_tones[1], &chord_tones[3]);
+        for voicing in &voicings {
+            let note_names : Vec<_> = voicing.points().iter()
+                .map(|point| fretboard.note_of_point(point).unwrap().note_name())
+                .collect();
+            assert!(note_names.contains(&G.natural()) && note_names.contains(third) && note_names.contains(seventh));
+        }
+        // Without the fifth, e.g. the shell voicing 3x34xx
+        assert!(voicings.iter().any(|voicing| voicing.omitted_tone_cnt() == 1));
+
+        let complete = VoicingConstraints { optional_tones: Vec::new(), ..VoicingConstraints::default() };
+        assert!(voicings_of_chord(&fretboard, &chord, &complete).unwrap().iter().all(|voicing| voicing.omitted_tone_cnt() == 0));
+    }
+
+    #[test]
+    fn suggested_fingers() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let c_major = voicings_of_chord(&fretboard, &Chord::of(C.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert_eq!(&[None, Some(1), None, Some(2), Some(3), None], c_major[0].fingers());
+        assert_eq!(None, c_major[0].barre());
+
+        let f_major = voicings_of_chord(&fretboard, &Chord::of(F.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert_eq!(&[Some(1), Some(1), Some(2), Some(4), Some(3), Some(1)], f_major[0].fingers());
+        let barre = f_major[0].barre().unwrap();
+        assert_eq!((1, 1, 6), (barre.fret(), barre.first_string(), barre.last_string()));
+    }
+
+    #[test]
+    fn hand_limits() {
+        let fretboard = Fretboard::of_fret_cnt(13);
+        let constraints = VoicingConstraints { max_fret_span: 2, max_finger_cnt: 2, ..VoicingConstraints::default() };
+        let voicings = voicings_of_chord(&fretboard, &Chord::of(A.natural(), ChordQuality::Minor), &constraints).unwrap();
+        assert!(!voicings.is_empty());
+        assert!(voicings.iter().all(|voicing| voicing.fret_span() <= 2 && voicing.finger_cnt() <= 2));
+        assert!(!representations(&voicings).contains(&"x02210".to_owned()));
+    }
+
+    #[test]
+    fn long_necks_with_many_strings() {
+        let fretboard = Fretboard::of_instrument(Instrument::by_name("8-String Guitar").unwrap(), 30);
+        let chord = Chord::of(C.natural(), ChordQuality::Dominant7Flat13);
+        let voicings = voicings_of_chord(&fretboard, &chord, &VoicingConstraints::default()).unwrap();
+        assert!(!voicings.is_empty());
+        assert!(voicings.iter().all(|voicing| voicing.fret_span() <= 4 && voicing.finger_cnt() <= 4 && voicing.omitted_tone_cnt() <= 1));
+    }
+
+    #[test]
+    fn open_strings_follow_the_capo() {
+        let fretboard = Fretboard::of_fret_cnt(13).with_capo(Some(Capo::full(2).unwrap())).unwrap();
+        // The open C shape sounds D
+        let d_major = voicings_of_chord(&fretboard, &Chord::of(D.natural(), ChordQuality::Major), &VoicingConstraints::default()).unwrap();
+        assert!(representations(&d_major).contains(&"x54030".to_owned()));
+        assert!(d_major.iter().all(|voicing| voicing.points().iter().all(|point| fretboard.contains(point))));
+    }
+}
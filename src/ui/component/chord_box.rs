@@ -0,0 +1,127 @@
+use crate::service::voicing::{Barre, Voicing};
+
+use eframe::egui::{
+    Align2, Color32, FontFamily, FontId, Painter, Pos2, Rect, Stroke
+};
+
+
+type StringName = u8;
+type FretNum = u8;
+
+/// Frets shown at least, even for a narrower shape
+const MIN_SHOWN_FRET_CNT : u8 = 4;
+
+/// 和弦图 A vertical chord box: strings standing from the lowest pitched on the left, frets going down from the nut,
+/// or from the starting fret written on the left. Muted strings are marked X and open ones O above the box,
+/// fretted ones with a dot carrying the suggested finger, the barre with a bar.
+pub struct ChordBoxComponent {
+    /// String 1 first, None for a muted string
+    frets : Vec<Option<FretNum>>,
+    /// String 1 first, 1 for the index finger
+    fingers : Vec<Option<u8>>,
+    barre : Option<Barre>,
+    /// Fret of the first row, 1 when the nut is shown
+    start_fret : FretNum,
+    shown_fret_cnt : u8,
+}
+
+impl ChordBoxComponent {
+    pub fn new(frets : Vec<Option<FretNum>>, fingers : Vec<Option<u8>>, barre : Option<Barre>) -> Self {
+        let fretted_frets = frets.iter().flatten().copied().filter(|fret| *fret != 0);
+        let (lowest_fret, highest_fret) = fretted_frets.fold((FretNum::MAX, 0), |(lowest, highest), fret| (lowest.min(fret), highest.max(fret)));
+        // Shapes close to the nut are shown from it
+        let start_fret = if highest_fret <= MIN_SHOWN_FRET_CNT { 1 } else { lowest_fret };
+        ChordBoxComponent {
+            frets,
+            fingers,
+            barre,
+            start_fret,
+            shown_fret_cnt: (highest_fret + 1).saturating_sub(start_fret).max(MIN_SHOWN_FRET_CNT),
+        }
+    }
+
+    pub fn of_voicing(voicing : &Voicing) -> Self {
+        Self::new(voicing.frets(), voicing.fingers().to_vec(), voicing.barre())
+    }
+
+    pub fn draw(&self, painter : &Painter, rect : Rect) {
+        painter.rect_filled(rect, 4.0, Color32::WHITE);
+        // Room above for X and O, on the left for the starting fret
+        let marker_height = rect.height() * 0.14;
+        let grid_rect = Rect::from_min_max(
+            Pos2::new(rect.left() + rect.width() * 0.22, rect.top() + marker_height + 4.),
+            Pos2::new(rect.right() - rect.width() * 0.08, rect.bottom() - 6.));
+        let row_height = grid_rect.height() / self.shown_fret_cnt as f32;
+        let dot_radius = (self.string_gap(grid_rect).min(row_height) * 0.38).max(3.);
+        let line_stroke = Stroke::new(1.0, Color32::BLACK);
+        let font = FontId::new((dot_radius * 1.4).max(9.), FontFamily::Proportional);
+
+        // Strings and frets
+        for string in self.string_names() {
+            painter.vline(self.string_x_pos(string, grid_rect), grid_rect.y_range(), line_stroke);
+        }
+        for row in 0..=self.shown_fret_cnt {
+            painter.hline(grid_rect.x_range(), grid_rect.top() + row as f32 * row_height, line_stroke);
+        }
+        if self.start_fret == 1 {
+            painter.hline(grid_rect.x_range(), grid_rect.top(), Stroke::new(4.0, Color32::BLACK));
+        } else {
+            painter.text(Pos2::new(grid_rect.left() - 4., grid_rect.top() + row_height / 2.), Align2::RIGHT_CENTER,
+                         format!("{}fr", self.start_fret), font.clone(), Color32::BLACK);
+        }
+
+        // X and O above the nut
+        for (string, fret) in self.string_names().zip(&self.frets) {
+            let marker_center = Pos2::new(self.string_x_pos(string, grid_rect), rect.top() + marker_height / 2. + 2.);
+            match fret {
+                None => {
+                    painter.text(marker_center, Align2::CENTER_CENTER, "X", font.clone(), Color32::BLACK);
+                },
+                Some(0) => {
+                    painter.circle_stroke(marker_center, dot_radius * 0.7, line_stroke);
+                },
+                Some(_) => {},
+            }
+        }
+
+        if let Some(barre) = self.barre {
+            let y_pos = self.row_center_y_pos(barre.fret(), grid_rect, row_height);
+            let barre_rect = Rect::from_x_y_ranges(
+                (self.string_x_pos(barre.last_string(), grid_rect) - dot_radius)..=(self.string_x_pos(barre.first_string(), grid_rect) + dot_radius),
+                (y_pos - dot_radius)..=(y_pos + dot_radius));
+            painter.rect_filled(barre_rect, dot_radius, Color32::BLACK);
+        }
+
+        // Dots with the suggested fingers
+        for ((string, fret), finger) in self.string_names().zip(&self.frets).zip(&self.fingers) {
+            let Some(fret) = fret.filter(|fret| *fret != 0) else {
+                continue;
+            };
+            let dot_center = Pos2::new(self.string_x_pos(string, grid_rect), self.row_center_y_pos(fret, grid_rect, row_height));
+            painter.circle_filled(dot_center, dot_radius, Color32::BLACK);
+            if let Some(finger) = finger {
+                painter.text(dot_center, Align2::CENTER_CENTER, finger.to_string(), font.clone(), Color32::WHITE);
+            }
+        }
+    }
+
+    fn string_names(&self) -> impl Iterator<Item = StringName> {
+        1..=self.frets.len() as StringName
+    }
+
+    fn string_gap(&self, grid_rect : Rect) -> f32 {
+        grid_rect.width() / self.frets.len().saturating_sub(1).max(1) as f32
+    }
+
+    /// The lowest pitched string, with the largest number, on the left; a single string in the middle
+    fn string_x_pos(&self, string : StringName, grid_rect : Rect) -> f32 {
+        if self.frets.len() == 1 {
+            return grid_rect.center().x;
+        }
+        grid_rect.left() + (self.frets.len() - string as usize) as f32 * self.string_gap(grid_rect)
+    }
+
+    fn row_center_y_pos(&self, fret : FretNum, grid_rect : Rect, row_height : f32) -> f32 {
+        grid_rect.top() + (fret.saturating_sub(self.start_fret) as f32 + 0.5) * row_height
+    }
+}
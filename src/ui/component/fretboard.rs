@@ -14,7 +14,11 @@ type FretNum = u8;
 
 struct StringComponent {
     center_y_pos: f32,
-    mouse_response_rect: Rect
+    mouse_response_rect: Rect,
+    /// Fret the nut of a short string sits on, 0 for a full length string
+    nut_fret: FretNum,
+    /// Fret of the capo if it covers the string, 0 otherwise
+    capo_fret: FretNum
 }
 
 enum FretMark {
@@ -70,12 +74,18 @@ impl FretboardComponent {
         let rect_fret_board_height = 1. - string_border * 2.;
 
         let string_gap_detect_ratio = 1.;
-        let string_detect_half_width : f32 = rect_fret_board_height / ( (string_cnt - 1) as f32 * (2. + string_gap_detect_ratio / 2.));
+        // A single string has no gap, it lies in the middle
+        let string_gap_cnt = string_cnt.saturating_sub(1).max(1);
+        let string_detect_half_width : f32 = rect_fret_board_height / ( string_gap_cnt as f32 * (2. + string_gap_detect_ratio / 2.));
 
         // Calculate the horizontal string representations
         let mut string_represent_vec : Vec<(StringName, StringComponent)> = Vec::new();
         for i in string_name_vec {
-            let center_y_unit_pos = rect_fret_board_top + ((i - 1) as f32 / (string_cnt - 1) as f32) * rect_fret_board_height;
+            let center_y_unit_pos = if string_cnt == 1 {
+                0.5
+            } else {
+                rect_fret_board_top + ((i - 1) as f32 / string_gap_cnt as f32) * rect_fret_board_height
+            };
 
             let mouse_response_unit_rect = Rect {
                 min : Pos2 { x: 0., y: center_y_unit_pos - string_detect_half_width },
@@ -84,7 +94,9 @@ impl FretboardComponent {
 
             let string_component = StringComponent {
                 center_y_pos: actual_area.top() + actual_area.height() * center_y_unit_pos,
-                mouse_response_rect: redeploy_by_parent(&mouse_response_unit_rect, &actual_area)
+                mouse_response_rect: redeploy_by_parent(&mouse_response_unit_rect, &actual_area),
+                nut_fret: fretboard.nut_fret_of_string(i),
+                capo_fret: fretboard.capo_fret_of_string(i)
             };
 
             string_represent_vec.push((i, string_component));
@@ -103,11 +115,11 @@ impl FretboardComponent {
             }
 
             for (fret_num, fret) in self.frets() {
-                if ! fret.mouse_response_rect.contains(mouse_pos) {
+                if ! fret.mouse_response_rect.contains(mouse_pos) || ! string.is_fret_playable(*fret_num) {
                     continue;
                 }
 
-                return Some((Point::of(*string_num, *fret_num),
+                return Some((Point::of(*string_num, *fret_num).ok()?,
                              string.mouse_response_rect.intersect(fret.mouse_response_rect)));
             }
         }
@@ -121,7 +133,7 @@ impl FretboardComponent {
             }
 
             for (fret_num, fret) in self.frets() {
-                if fret_num != point.behind_fret() {
+                if fret_num != point.behind_fret() || ! string.is_fret_playable(*fret_num) {
                     continue;
                 }
 
@@ -140,14 +152,40 @@ impl FretboardComponent {
             painter.vline(x_pos, rect.y_range(), Stroke::new(2.0, Color32::GRAY));
         }
 
-        // Draw the horizontal strings
+        // Draw the horizontal strings, a short string from its nut on
         for (string_num, string) in &self.strings {
             let y_pos = string.center_y_pos;
             let string_stroke = Stroke::new(
-                1. + string_num.clone() as f32 * 0.3, // Vary thickness for different strings
+                1. + *string_num as f32 * 0.3, // Vary thickness for different strings
                 Color32::from_gray(200),
             );
-            painter.hline(rect.x_range(), y_pos, string_stroke);
+            let left_x_pos = self.frets.iter()
+                .find(|(fret_num, _)| string.nut_fret != 0 && *fret_num == string.nut_fret)
+                .map_or(rect.left(), |(_, fret)| fret.fret_x_pos);
+            painter.hline(left_x_pos..=rect.right(), y_pos, string_stroke);
+            if left_x_pos > rect.left() {
+                painter.circle_filled(Pos2::new(left_x_pos, y_pos), 4.0, Color32::from_gray(230));
+            }
+        }
+
+        self.draw_capo(painter);
+    }
+
+    /// The capo is drawn as a bar just behind its fret, across the strings it covers.
+    fn draw_capo(&self, painter: &Painter) {
+        for (fret_num, fret) in &self.frets {
+            let covered_y_positions = self.strings.iter()
+                .filter(|(_, string)| string.capo_fret != 0 && string.capo_fret == *fret_num)
+                .map(|(_, string)| string.center_y_pos);
+            let (top, bottom) = covered_y_positions.fold((f32::INFINITY, f32::NEG_INFINITY),
+                |(top, bottom), y_pos| (top.min(y_pos), bottom.max(y_pos)));
+            if top > bottom {
+                continue;
+            }
+            let capo_rect = Rect::from_x_y_ranges(
+                (fret.fret_x_pos - 12.)..=(fret.fret_x_pos - 4.),
+                (top - 8.)..=(bottom + 8.));
+            painter.rect_filled(capo_rect, 3.0, Color32::from_gray(30));
         }
     }
 
@@ -171,6 +209,13 @@ impl FretboardComponent {
         }
     }
 }
+
+impl StringComponent {
+    fn is_fret_playable(&self, fret_num : FretNum) -> bool {
+        fret_num == 0 || fret_num > self.nut_fret.max(self.capo_fret)
+    }
+}
+
 fn redeploy_by_parent(cur_rect : &Rect, parent : &Rect) -> Rect {
     let parent_size = parent.size();
     let parent_translate = parent.left_top();
@@ -1 +1,2 @@
-pub mod fretboard;
\ No newline at end of file
+pub mod fretboard;
+pub mod chord_box;
\ No newline at end of file
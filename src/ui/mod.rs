@@ -1,3 +1,4 @@
 pub mod component;
 
-pub mod window;
\ No newline at end of file
+pub mod window;
+pub mod quiz;
\ No newline at end of file
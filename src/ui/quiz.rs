@@ -0,0 +1,348 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui::{Color32, ComboBox, FontFamily, FontId, Key, RichText, Stroke, StrokeKind, Ui};
+use fastrand::Rng;
+
+use crate::guitar::fretboard::{Fretboard, Point};
+use crate::guitar::region::Region;
+use crate::music::note::NoteName;
+use crate::service::quiz::{AccidentalSpelling, FindNoteFeedback, FindNoteQuiz, NameNoteFeedback, NameNoteQuiz, QuizMode, QuizRoundRecord, QuizScore};
+use crate::service::saved_state::{FindNoteSettings, NameNoteSettings, Progress};
+use crate::service::spaced_repetition::{AnswerTiming, ReviewRecord};
+use crate::ui::component::fretboard::FretboardComponent;
+use crate::ui::window::FretboardApp;
+
+const ROUND_SECS_CHOICES : [u64; 4] = [30, 60, 120, 300];
+/// Rounds listed under the quiz
+const SHOWN_HISTORY_CNT : usize = 5;
+
+/// 找音测验面板 Controls, question, score and timer of the "Find the note" quiz
+pub struct FindNoteQuizPanel {
+    rng : Rng,
+    is_everywhere : bool,
+    round_secs : u64,
+    quiz : Option<FindNoteQuiz>,
+    round_started_at : Option<Instant>,
+    /// Since the question was asked or the last click
+    asked_at : Instant,
+}
+
+impl Default for FindNoteQuizPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FindNoteQuizPanel {
+    pub fn new() -> FindNoteQuizPanel {
+        FindNoteQuizPanel::of_settings(FindNoteSettings::default())
+    }
+
+    pub fn of_settings(settings : FindNoteSettings) -> FindNoteQuizPanel {
+        FindNoteQuizPanel {
+            rng: Rng::new(),
+            is_everywhere: settings.is_everywhere,
+            round_secs: settings.round_secs,
+            quiz: None,
+            round_started_at: None,
+            asked_at: Instant::now(),
+        }
+    }
+
+    pub fn settings(&self) -> FindNoteSettings {
+        FindNoteSettings { is_everywhere: self.is_everywhere, round_secs: self.round_secs }
+    }
+
+    fn time_left(&self) -> Duration {
+        self.round_started_at.map_or(Duration::ZERO, |started_at| {
+            Duration::from_secs(self.round_secs).saturating_sub(started_at.elapsed())
+        })
+    }
+
+    /// A round started and its time isn't up
+    pub fn is_running(&self) -> bool {
+        self.quiz.is_some() && !self.time_left().is_zero()
+    }
+
+    /// Ends the round, e.g. when the fretboard changes under it or the time is up. Its record, if anything was answered.
+    pub fn stop(&mut self) -> Option<QuizRoundRecord> {
+        self.round_started_at.take()?;
+        round_record(QuizMode::FindNote, self.quiz.as_ref()?.score())
+    }
+
+    fn start(&mut self, fretboard : &Fretboard, region : &Region, progress : &mut Progress) {
+        progress.quiz_history.extend(self.stop());
+        self.quiz = FindNoteQuiz::new(fretboard, &fretboard.points_in(region), self.is_everywhere, &progress.practice_schedule, unix_now(), &mut self.rng);
+        self.round_started_at = Some(Instant::now());
+        self.asked_at = Instant::now();
+    }
+
+    /// Notes are asked and found in the region only.
+    pub fn show_controls(&mut self, ui : &mut Ui, fretboard : &Fretboard, region : &Region, progress : &mut Progress) {
+        if !self.is_running() {
+            progress.quiz_history.extend(self.stop());
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.is_everywhere, "Find every position");
+            ComboBox::from_label("Round")
+                .selected_text(format!("{} s", self.round_secs))
+                .show_ui(ui, |ui| {
+                    for secs in ROUND_SECS_CHOICES {
+                        ui.selectable_value(&mut self.round_secs, secs, format!("{secs} s"));
+                    }
+                });
+            if ui.button(if self.is_running() { "Restart round" } else { "Start round" }).clicked() {
+                self.start(fretboard, region, progress);
+            }
+        });
+
+        let Some(quiz) = &self.quiz else {
+            show_history(ui, progress, QuizMode::FindNote);
+            return;
+        };
+        let question_text = if self.is_running() {
+            format!("Find {}", quiz.question().string_representation())
+        } else {
+            "Time's up!".to_owned()
+        };
+        let feedback_text = match quiz.last_feedback() {
+            None => "",
+            Some(FindNoteFeedback::Correct) => "Correct!",
+            Some(FindNoteFeedback::AlreadyFound) => "Already found",
+            Some(FindNoteFeedback::Incorrect { .. }) => "Incorrect",
+            Some(FindNoteFeedback::Unplayable) => "Nothing sounds there, not counted",
+            Some(FindNoteFeedback::OutsideRegion) => "Outside the region, not counted",
+        };
+        ui.label(RichText::new(question_text).font(FontId::new(24.0, FontFamily::Proportional)));
+        ui.label(RichText::new(feedback_text).font(FontId::new(19.0, FontFamily::Proportional)));
+        ui.label(RichText::new(score_text(quiz.score(), Some(self.time_left()))).font(FontId::new(19.0, FontFamily::Monospace)));
+        if self.is_running() {
+            ui.ctx().request_repaint_after(Duration::from_millis(200));
+        }
+        show_history(ui, progress, QuizMode::FindNote);
+    }
+
+    pub fn answer(&mut self, fretboard : &Fretboard, region : &Region, point : Point, progress : &mut Progress) {
+        if !self.is_running() {
+            return;
+        }
+        if let Some(quiz) = &mut self.quiz {
+            quiz.answer(fretboard, &fretboard.points_in(region), point, &mut progress.practice_schedule, answer_timing(self.asked_at), &mut self.rng);
+            self.asked_at = Instant::now();
+        }
+    }
+
+    /// Found points in green, the last wrong click in red with the note it sounds.
+    pub fn show_feedback(&self, ui : &mut Ui, fretboard : &Fretboard, fret_board_component : &FretboardComponent) {
+        let Some(quiz) = &self.quiz else {
+            return;
+        };
+        for point in quiz.question().found() {
+            if let (Ok(note), Some(point_rect)) =
+                (fretboard.note_of_point(point), fret_board_component.get_rect_on_point(*point)) {
+                FretboardApp::show_note_in_rect(ui, note, point_rect, Color32::LIGHT_GREEN);
+            }
+        }
+        if let Some(FindNoteFeedback::Incorrect { point, note }) = quiz.last_feedback()
+            && let Some(point_rect) = fret_board_component.get_rect_on_point(*point) {
+            FretboardApp::show_note_in_rect(ui, note.clone(), point_rect, Color32::LIGHT_RED);
+        }
+    }
+}
+
+/// 认音测验面板 Settings, answer buttons and score of the "Name the note" quiz
+pub struct NameNoteQuizPanel {
+    rng : Rng,
+    spelling : AccidentalSpelling,
+    quiz : Option<NameNoteQuiz>,
+    typed_answer : String,
+    typed_answer_error : Option<String>,
+    asked_at : Instant,
+}
+
+impl Default for NameNoteQuizPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NameNoteQuizPanel {
+    pub fn new() -> NameNoteQuizPanel {
+        NameNoteQuizPanel::of_settings(NameNoteSettings::default())
+    }
+
+    pub fn of_settings(settings : NameNoteSettings) -> NameNoteQuizPanel {
+        NameNoteQuizPanel {
+            rng: Rng::new(),
+            spelling: settings.spelling,
+            quiz: None,
+            typed_answer: String::new(),
+            typed_answer_error: None,
+            asked_at: Instant::now(),
+        }
+    }
+
+    pub fn settings(&self) -> NameNoteSettings {
+        NameNoteSettings { spelling: self.spelling }
+    }
+
+    /// Ends the quiz, e.g. when the fretboard changes under it. Its record, if anything was answered.
+    pub fn stop(&mut self) -> Option<QuizRoundRecord> {
+        round_record(QuizMode::NameNote, self.quiz.take()?.score())
+    }
+
+    /// Points are asked in the region only.
+    pub fn show_controls(&mut self, ui : &mut Ui, fretboard : &Fretboard, region : &Region, progress : &mut Progress) {
+        ui.horizontal(|ui| {
+            let mut spelling_changed = false;
+            ComboBox::from_label("Accepted spelling")
+                .selected_text(self.spelling.string_representation())
+                .show_ui(ui, |ui| {
+                    for spelling in AccidentalSpelling::ALL {
+                        spelling_changed |= ui.selectable_value(&mut self.spelling, spelling, spelling.string_representation()).changed();
+                    }
+                });
+            // The running quiz takes it for the next answers, its choices redrawn below
+            if spelling_changed && let Some(quiz) = &mut self.quiz {
+                quiz.set_spelling(self.spelling);
+            }
+            if ui.button(if self.quiz.is_some() { "Restart" } else { "Start" }).clicked() {
+                progress.quiz_history.extend(self.stop());
+                self.quiz = NameNoteQuiz::new(fretboard, &fretboard.points_in(region), self.spelling, &progress.practice_schedule, unix_now(), &mut self.rng);
+                self.typed_answer_error = None;
+                self.asked_at = Instant::now();
+            }
+            if self.quiz.is_some() && ui.button("Finish").clicked() {
+                progress.quiz_history.extend(self.stop());
+            }
+        });
+
+        let Some(quiz) = &self.quiz else {
+            show_history(ui, progress, QuizMode::NameNote);
+            return;
+        };
+        ui.label(RichText::new("Name the highlighted note").font(FontId::new(24.0, FontFamily::Proportional)));
+        let mut answer = None;
+        ui.horizontal(|ui| {
+            for note_name in quiz.spelling().answer_choices() {
+                if ui.button(RichText::new(answer_choice_text(&note_name, quiz.spelling())).font(FontId::new(19.0, FontFamily::Monospace))).clicked() {
+                    answer = Some(note_name);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let typed_answer_response = ui.text_edit_singleline(&mut self.typed_answer);
+            let is_submitted = typed_answer_response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+            if ui.button("Answer").clicked() || is_submitted {
+                match self.typed_answer.trim().parse::<NoteName>() {
+                    Ok(note_name) => {
+                        answer = Some(note_name);
+                        self.typed_answer.clear();
+                        self.typed_answer_error = None;
+                    },
+                    Err(err) => self.typed_answer_error = Some(err.to_string()),
+                }
+                typed_answer_response.request_focus();
+            }
+            if let Some(err) = &self.typed_answer_error {
+                ui.colored_label(Color32::RED, err);
+            }
+        });
+
+        if let Some(answer) = answer {
+            let candidates = fretboard.points_in(region);
+            if let Some(quiz) = &mut self.quiz {
+                quiz.answer(fretboard, &candidates, answer, &mut progress.practice_schedule, answer_timing(self.asked_at), &mut self.rng);
+                self.asked_at = Instant::now();
+            }
+        }
+
+        let Some(quiz) = &self.quiz else {
+            show_history(ui, progress, QuizMode::NameNote);
+            return;
+        };
+        let feedback_text = match quiz.last_feedback() {
+            None => String::new(),
+            Some(NameNoteFeedback::Correct { note, .. }) => format!("Correct! It was {}", note.clone().string_representation()),
+            Some(NameNoteFeedback::Incorrect { note, answer, .. }) => format!("Incorrect: not {}, it was {}",
+                answer.clone().string_representation(), note.clone().string_representation()),
+        };
+        ui.label(RichText::new(feedback_text).font(FontId::new(19.0, FontFamily::Proportional)));
+        ui.label(RichText::new(score_text(quiz.score(), None)).font(FontId::new(19.0, FontFamily::Monospace)));
+        // 历史 All-time record of the point just answered
+        if let Some(NameNoteFeedback::Correct { point, .. } | NameNoteFeedback::Incorrect { point, .. }) = quiz.last_feedback()
+            && let Some(record) = progress.practice_schedule.record_of_point(fretboard, point) {
+            ui.label(RichText::new(record_text(record)).font(FontId::new(19.0, FontFamily::Monospace)));
+        }
+        show_history(ui, progress, QuizMode::NameNote);
+    }
+
+    /// Highlights the asked point without naming it.
+    pub fn show_question(&self, ui : &mut Ui, fret_board_component : &FretboardComponent) {
+        if let Some(quiz) = &self.quiz
+            && let Some(point_rect) = fret_board_component.get_rect_on_point(quiz.point()) {
+            ui.painter().rect(point_rect, 10, Color32::GOLD, Stroke::new(2.0, Color32::BLACK), StrokeKind::Inside);
+        }
+    }
+}
+
+/// e.g. "C#/Db" when both spellings are accepted
+fn answer_choice_text(note_name : &NoteName, spelling : AccidentalSpelling) -> String {
+    let flat_spelled = NoteName::of_pitch_class_as_flat(note_name.integer_notation());
+    if spelling == AccidentalSpelling::Both && flat_spelled.accidental_cnt() != note_name.accidental_cnt() {
+        format!("{}/{}", note_name.clone().string_representation(), flat_spelled.string_representation())
+    } else {
+        note_name.clone().string_representation()
+    }
+}
+
+/// e.g. "This position: 3/4 correct, 2.1 s on average, box 2"
+fn record_text(record : &ReviewRecord) -> String {
+    format!("This position: {}/{} correct, {:.1} s on average, box {}",
+        record.correct_cnt(),
+        record.correct_cnt() + record.incorrect_cnt(),
+        record.average_response_time().unwrap_or_default().as_secs_f32(),
+        record.leitner_box())
+}
+
+fn round_record(mode : QuizMode, score : &QuizScore) -> Option<QuizRoundRecord> {
+    (score.correct_cnt() + score.incorrect_cnt() != 0)
+        .then(|| QuizRoundRecord { mode, finished_at: unix_now(), score: *score })
+}
+
+/// 历史 The last rounds of the mode, newest first
+fn show_history(ui : &mut Ui, progress : &Progress, mode : QuizMode) {
+    let records : Vec<&QuizRoundRecord> = progress.quiz_history.iter().rev()
+        .filter(|record| record.mode == mode)
+        .take(SHOWN_HISTORY_CNT)
+        .collect();
+    if records.is_empty() {
+        return;
+    }
+    ui.label(RichText::new("Previous rounds:").font(FontId::new(19.0, FontFamily::Proportional)));
+    for record in records {
+        ui.label(RichText::new(score_text(&record.score, None)).font(FontId::new(15.0, FontFamily::Monospace)));
+    }
+}
+
+/// Seconds since the Unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+fn answer_timing(asked_at : Instant) -> AnswerTiming {
+    AnswerTiming { response_time: asked_at.elapsed(), answered_at: unix_now() }
+}
+
+fn score_text(score : &QuizScore, time_left : Option<Duration>) -> String {
+    let score_text = format!("Score: {}/{}   Streak: {} (best {})   Accuracy: {}",
+        score.correct_cnt(),
+        score.correct_cnt() + score.incorrect_cnt(),
+        score.streak(),
+        score.best_streak(),
+        score.accuracy().map_or("-".to_owned(), |accuracy| format!("{:.0}%", accuracy * 100.)));
+    match time_left {
+        Some(time_left) => format!("{score_text}   Time left: {} s", time_left.as_secs()),
+        None => score_text,
+    }
+}
@@ -1,164 +1,788 @@
 pub(crate) use crate::{
     guitar::fretboard::Fretboard, ui::component::fretboard::FretboardComponent,
 };
-use std::cell::Cell;
 
+use crate::guitar::capo::Capo;
 use crate::guitar::fretboard::Point;
+use crate::guitar::instrument::{Instrument, INSTRUMENTS};
+use crate::guitar::region::Region;
+use crate::guitar::tuning::Tuning;
 use eframe::egui::{Rect, RichText, Ui};
 use eframe::{
-    App,
-    egui::{Align2, CentralPanel, Color32, FontFamily, FontId, Sense, Vec2},
+    App, Storage,
+    egui::{Align2, CentralPanel, Color32, ComboBox, DragValue, FontFamily, FontId, Sense, Slider, Vec2},
 };
 use eframe::epaint::Hsva;
-use crate::music::note::Note;
-use crate::music::scale;
-use crate::service::scale_map;
+use crate::music::note::{NaturalNoteName, Note, NoteName};
+use crate::music::chord::{Chord, ChordMatch, ChordQuality};
+use crate::music::scale::ScaleMatch;
+use crate::music::scale_catalog::{ScaleKind, SCALE_CATALOG};
+use crate::service::{recognition, scale_map};
+use crate::service::note_label::LabelMode;
+use crate::service::voicing::{self, Voicing, VoicingConstraints};
+use crate::service::caged::{self, CagedPosition, CagedShape};
+use crate::service::fingering::{FingeringKind, ScaleFingering};
+use crate::ui::component::chord_box::ChordBoxComponent;
+use crate::service::saved_state::{Progress, SavedState, Settings, PRACTICE_SCHEDULE_KEY_V1, SAVED_STATE_KEY};
+use crate::ui::quiz::{FindNoteQuizPanel, NameNoteQuizPanel};
+
+/// Roots offered for scales and chords, black keys spelled both ways
+const ROOT_CHOICES : [NoteName; 17] = [
+    NaturalNoteName::C.natural(), NaturalNoteName::C.sharp(), NaturalNoteName::D.flat(), NaturalNoteName::D.natural(),
+    NaturalNoteName::D.sharp(), NaturalNoteName::E.flat(), NaturalNoteName::E.natural(), NaturalNoteName::F.natural(),
+    NaturalNoteName::F.sharp(), NaturalNoteName::G.flat(), NaturalNoteName::G.natural(), NaturalNoteName::G.sharp(),
+    NaturalNoteName::A.flat(), NaturalNoteName::A.natural(), NaturalNoteName::A.sharp(), NaturalNoteName::B.flat(),
+    NaturalNoteName::B.natural(),
+];
+
+const MIN_FRET_BAR_CNT : u8 = 5;
+const MAX_FRET_BAR_CNT : u8 = 30;
+/// Voicings drawn as chord boxes, the most playable ones
+const SHOWN_VOICING_CNT : usize = 8;
+
+/// 模式 What clicks on the fretboard do
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+enum AppMode {
+    /// Select notes, pick chord points, show scale maps
+    Explore,
+    /// Click the positions of the asked note
+    FindNote,
+    /// Name the note of the highlighted point
+    NameNote,
+}
 
 pub struct FretboardApp {
+    mode : AppMode,
     fretboard: Fretboard,
-    cur_select_point : Cell<Option<Point>>,
-    show_scale_map : Cell<bool>,
+    instrument : &'static Instrument,
+    cur_select_point : Option<Point>,
+    show_scale_map : bool,
+    /// Root and kind of the scale shown on the fretboard, apart from the selected point
+    scale_root : NoteName,
+    scale_kind : &'static ScaleKind,
+    /// Kind of the fingering shown instead of the whole scale map, None for the whole map
+    fingering_kind : Option<FingeringKind>,
+    /// Index of the fingering shown, from the nut up
+    fingering_index : usize,
+    /// How the notes drawn on the fretboard are labelled, relative to `scale_root` for the relative modes
+    label_mode : LabelMode,
+    /// Highlights every position of the selected note's pitch class
+    show_note_positions : bool,
+    /// Points picked with shift-click to name a chord, at most one per string
+    chord_points : Vec<Point>,
+    /// Chord whose voicings are drawn as chord boxes
+    voicing_chord : Chord,
+    /// Voicings of `voicing_chord` on the fretboard, computed again when either changes
+    voicings : Vec<Voicing>,
+    /// CAGED positions of `voicing_chord` on the fretboard, computed along with `voicings`
+    caged_positions : Vec<CagedPosition>,
+    /// Index of the CAGED position of `voicing_chord` highlighted, from the nut up; None for none
+    caged_position_index : Option<usize>,
+    /// Text of a user tuning, lowest pitched string first
+    custom_tuning_text : String,
+    custom_tuning_error : Option<String>,
+    /// Fret of the capo, 0 for no capo
+    capo_fret : u8,
+    /// Strings left uncovered by a partial capo
+    capo_skipped_strings : Vec<u8>,
+    /// Frets and strings the scale map, the positions and the quizzes keep to
+    region : Region,
+    find_note_quiz : FindNoteQuizPanel,
+    name_note_quiz : NameNoteQuizPanel,
+    /// Kept between runs in the app storage, with the settings
+    progress : Progress,
+    /// The saved state could not be loaded, it is then left as is rather than overwritten.
+    storage_error : Option<String>,
+}
+
+impl Default for FretboardApp {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FretboardApp {
     pub fn new() -> FretboardApp {
-        FretboardApp {
-            fretboard: Fretboard::of_fret_cnt(14),
-            cur_select_point: Cell::new(None),
-            show_scale_map: Cell::new(false),
+        let mut app = FretboardApp {
+            mode: AppMode::Explore,
+            fretboard: Fretboard::of_instrument(&INSTRUMENTS[0], 14),
+            instrument: &INSTRUMENTS[0],
+            cur_select_point: None,
+            show_scale_map: false,
+            scale_root: NaturalNoteName::C.natural(),
+            scale_kind: &SCALE_CATALOG[0],
+            fingering_kind: None,
+            fingering_index: 0,
+            label_mode: LabelMode::default(),
+            show_note_positions: false,
+            chord_points: Vec::new(),
+            voicing_chord: Chord::of(NaturalNoteName::C.natural(), ChordQuality::Major),
+            voicings: Vec::new(),
+            caged_positions: Vec::new(),
+            caged_position_index: None,
+            custom_tuning_text: Tuning::standard().string_representation(),
+            custom_tuning_error: None,
+            capo_fret: 0,
+            capo_skipped_strings: Vec::new(),
+            region: Region::whole(),
+            find_note_quiz: FindNoteQuizPanel::new(),
+            name_note_quiz: NameNoteQuizPanel::new(),
+            progress: Progress::default(),
+            storage_error: None,
+        };
+        app.update_voicings();
+        app
+    }
+
+    /// Restores the settings and the progress saved by the previous runs.
+    pub fn of_storage(storage : Option<&dyn Storage>) -> FretboardApp {
+        let mut app = FretboardApp::new();
+        let Some(storage) = storage else {
+            return app;
+        };
+        match SavedState::load(storage.get_string(SAVED_STATE_KEY).as_deref(), storage.get_string(PRACTICE_SCHEDULE_KEY_V1).as_deref()) {
+            Ok(saved_state) => {
+                app.apply_settings(saved_state.settings);
+                app.progress = saved_state.progress;
+            },
+            Err(err) => app.storage_error = Some(err.to_string()),
+        }
+        app
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            instrument_name: self.instrument.name().to_owned(),
+            tuning_name: self.fretboard.tuning().name().to_owned(),
+            tuning_notes: self.fretboard.tuning().string_representation(),
+            fret_cnt: self.fretboard.fret_bar_cnt(),
+            capo_fret: self.capo_fret,
+            capo_skipped_strings: self.capo_skipped_strings.clone(),
+            scale_root: self.scale_root.clone().string_representation(),
+            scale_name: self.scale_kind.name().to_owned(),
+            show_scale_map: self.show_scale_map,
+            region: self.region.clone(),
+            label_mode: self.label_mode,
+            find_note: self.find_note_quiz.settings(),
+            name_note: self.name_note_quiz.settings(),
+        }
+    }
+
+    /// Settings not making sense on this build, like an unknown instrument, keep their default.
+    fn apply_settings(&mut self, settings : Settings) {
+        let instrument = Instrument::by_name(&settings.instrument_name).unwrap_or(&INSTRUMENTS[0]);
+        self.instrument = instrument;
+        let mut fretboard = Fretboard::of_instrument(instrument, settings.fret_cnt.clamp(MIN_FRET_BAR_CNT, MAX_FRET_BAR_CNT));
+        if let Ok(tuning) = settings.tuning_notes.parse::<Tuning>()
+            && let Ok(tuning) = Tuning::of(&settings.tuning_name, tuning.zero_fret_notes().to_vec()) {
+            fretboard = fretboard.with_tuning(tuning);
+        }
+        self.set_fretboard(fretboard);
+        self.capo_fret = settings.capo_fret;
+        self.capo_skipped_strings = settings.capo_skipped_strings;
+        self.apply_capo();
+        if let Ok(scale_root) = settings.scale_root.parse::<NoteName>() {
+            self.scale_root = scale_root;
+        }
+        if let Some(scale_kind) = ScaleKind::by_name(&settings.scale_name) {
+            self.scale_kind = scale_kind;
+        }
+        self.show_scale_map = settings.show_scale_map;
+        self.region = settings.region;
+        self.label_mode = settings.label_mode;
+        self.find_note_quiz = FindNoteQuizPanel::of_settings(settings.find_note);
+        self.name_note_quiz = NameNoteQuizPanel::of_settings(settings.name_note);
+    }
+
+    fn set_tuning(&mut self, tuning: Tuning) {
+        self.set_fretboard(self.fretboard.with_tuning(tuning));
+    }
+
+    fn set_instrument(&mut self, instrument: &'static Instrument) {
+        self.instrument = instrument;
+        self.set_fretboard(Fretboard::of_instrument(instrument, self.fretboard.fret_bar_cnt()));
+    }
+
+    fn set_fretboard(&mut self, fretboard: Fretboard) {
+        self.fretboard = fretboard;
+        self.progress.quiz_history.extend(self.find_note_quiz.stop());
+        self.progress.quiz_history.extend(self.name_note_quiz.stop());
+        self.capo_fret = self.fretboard.capo().map_or(0, Capo::fret);
+        let string_name_vec = self.fretboard.string_name_vec();
+        self.capo_skipped_strings.retain(|string| string_name_vec.contains(string));
+        self.custom_tuning_text = self.fretboard.tuning().string_representation();
+        self.custom_tuning_error = None;
+        // Points may be on strings that are gone or before the nut of a short string
+        self.chord_points.retain(|point| self.fretboard.contains(point));
+        if let Some(point) = self.cur_select_point
+            && !self.fretboard.contains(&point) {
+            self.cur_select_point = None;
+        }
+        self.update_voicings();
+    }
+
+    /// Voicings and CAGED positions of the chord, kept until the chord or the fretboard changes
+    fn update_voicings(&mut self) {
+        self.voicings = voicing::voicings_of_chord(&self.fretboard, &self.voicing_chord, &VoicingConstraints::default())
+            .unwrap_or_default();
+        self.caged_positions = caged::caged_positions(&self.fretboard, &self.voicing_chord);
+    }
+
+    /// 和弦指法 Chord to voice, its most playable voicings as chord boxes; clicking one shows it on the fretboard
+    fn show_voicings(&mut self, ui: &mut Ui) {
+        let mut chord = self.voicing_chord.clone();
+        ui.horizontal(|ui| {
+            let root_text = chord.root().string_representation();
+            ComboBox::from_label("Chord root")
+                .selected_text(&root_text)
+                .show_ui(ui, |ui| {
+                    for root in ROOT_CHOICES {
+                        let text = root.clone().string_representation();
+                        if ui.selectable_label(text == root_text, &text).clicked() {
+                            chord = Chord::of(root, chord.quality());
+                        }
+                    }
+                });
+            ComboBox::from_label("Voicings of")
+                .selected_text(chord.clone().string_representation())
+                .show_ui(ui, |ui| {
+                    for quality in ChordQuality::ALL {
+                        let quality_chord = Chord::of(chord.root(), quality);
+                        if ui.selectable_label(quality == chord.quality(), quality_chord.clone().string_representation()).clicked() {
+                            chord = quality_chord;
+                        }
+                    }
+                });
+        });
+        if chord != self.voicing_chord {
+            self.voicing_chord = chord;
+            self.update_voicings();
+        }
+
+        ui.horizontal(|ui| {
+            if self.voicings.is_empty() {
+                ui.label("No playable voicing");
+            }
+            let mut picked_points = None;
+            for voicing in self.voicings.iter().take(SHOWN_VOICING_CNT) {
+                ui.vertical(|ui| {
+                    let (box_rect, box_response) = ui.allocate_exact_size(Vec2::new(100.0, 120.0), Sense::click());
+                    ChordBoxComponent::of_voicing(voicing).draw(ui.painter(), box_rect);
+                    ui.label(voicing.string_representation());
+                    if box_response.on_hover_text("Show on the fretboard").clicked() {
+                        picked_points = Some(voicing.points());
+                    }
+                });
+            }
+            if let Some(points) = picked_points {
+                self.chord_points = points;
+            }
+        });
+    }
+
+    /// 乐器与定弦选择 Instrument presets, the tuning presets with as many strings, and a text field for a user tuning
+    fn show_tuning_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut selected_instrument = None;
+            ComboBox::from_label("Instrument")
+                .selected_text(self.instrument.name())
+                .show_ui(ui, |ui| {
+                    for instrument in INSTRUMENTS {
+                        if ui.selectable_label(std::ptr::eq(self.instrument, instrument), instrument.name()).clicked() {
+                            selected_instrument = Some(instrument);
+                        }
+                    }
+                });
+            if let Some(instrument) = selected_instrument {
+                self.set_instrument(instrument);
+            }
+
+            let mut fret_bar_cnt = self.fretboard.fret_bar_cnt();
+            ui.label("Frets:");
+            if ui.add(DragValue::new(&mut fret_bar_cnt).range(MIN_FRET_BAR_CNT..=MAX_FRET_BAR_CNT)).changed() {
+                self.set_fretboard(self.fretboard.with_fret_bar_cnt(fret_bar_cnt));
+            }
+
+            let mut selected_preset = None;
+            ComboBox::from_label("Tuning")
+                .selected_text(self.fretboard.tuning().name().to_owned())
+                .show_ui(ui, |ui| {
+                    for preset in self.instrument.tuning_presets() {
+                        let is_selected = self.fretboard.tuning().zero_fret_notes() == preset.zero_fret_notes();
+                        if ui.selectable_label(is_selected, preset.name()).clicked() {
+                            selected_preset = Some(preset.tuning());
+                        }
+                    }
+                });
+            if let Some(tuning) = selected_preset {
+                self.set_tuning(tuning);
+            }
+
+            ui.text_edit_singleline(&mut self.custom_tuning_text);
+            if ui.button("Apply tuning").clicked() {
+                match self.custom_tuning_text.parse::<Tuning>() {
+                    Ok(tuning) => self.set_tuning(tuning),
+                    Err(err) => self.custom_tuning_error = Some(err.to_string()),
+                }
+            }
+            if let Some(err) = &self.custom_tuning_error {
+                ui.colored_label(Color32::RED, err);
+            }
+        });
+    }
+
+    /// 变调夹 Capo fret and the strings it covers
+    fn show_capo_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut capo_changed = false;
+            ComboBox::from_label("Capo")
+                .selected_text(match self.capo_fret {
+                    0 => "None".to_owned(),
+                    fret => format!("Fret {fret}"),
+                })
+                .show_ui(ui, |ui| {
+                    for fret in 0..self.fretboard.fret_bar_cnt().min(13) {
+                        let text = if fret == 0 { "None".to_owned() } else { format!("Fret {fret}") };
+                        capo_changed |= ui.selectable_value(&mut self.capo_fret, fret, text).changed();
+                    }
+                });
+            if self.capo_fret != 0 {
+                ui.label("Covers strings:");
+                for string in self.fretboard.string_name_vec() {
+                    let mut is_covered = !self.capo_skipped_strings.contains(&string);
+                    if ui.checkbox(&mut is_covered, string.to_string()).changed() {
+                        if is_covered {
+                            self.capo_skipped_strings.retain(|skipped| *skipped != string);
+                        } else {
+                            self.capo_skipped_strings.push(string);
+                        }
+                        capo_changed = true;
+                    }
+                }
+            }
+            if capo_changed {
+                self.apply_capo();
+            }
+        });
+    }
+
+    /// 区域 Frets and strings to focus on; the highest fret at the last one keeps up with the fret count
+    fn show_region_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let last_fret = self.fretboard.fret_bar_cnt().saturating_sub(1);
+            let frets = self.region.frets();
+            let (mut lowest_fret, mut highest_fret) = ((*frets.start()).min(last_fret), (*frets.end()).min(last_fret));
+            let mut skipped_strings = self.region.skipped_strings().to_vec();
+            let mut region_changed = false;
+            ui.label("Focus on frets");
+            region_changed |= ui.add(Slider::new(&mut lowest_fret, 0..=last_fret)).changed();
+            ui.label("to");
+            region_changed |= ui.add(Slider::new(&mut highest_fret, 0..=last_fret)).changed();
+            ui.label("strings");
+            for string in self.fretboard.string_name_vec() {
+                let mut is_included = self.region.includes_string(string);
+                if ui.checkbox(&mut is_included, string.to_string()).changed() {
+                    if is_included {
+                        skipped_strings.retain(|skipped| *skipped != string);
+                    } else {
+                        skipped_strings.push(string);
+                    }
+                    region_changed = true;
+                }
+            }
+            if region_changed {
+                let highest_fret = if highest_fret == last_fret { u8::MAX } else { highest_fret };
+                self.region = Region::of(lowest_fret..=highest_fret, skipped_strings);
+            }
+            if !self.region.is_whole() && ui.button("Whole neck").clicked() {
+                self.region = Region::whole();
+            }
+        });
+    }
+
+    fn apply_capo(&mut self) {
+        let capo = match self.capo_fret {
+            0 => Ok(None),
+            fret if self.capo_skipped_strings.is_empty() => Capo::full(fret).map(Some),
+            fret => Capo::partial(fret, self.fretboard.string_name_vec().into_iter()
+                .filter(|string| !self.capo_skipped_strings.contains(string))
+                .collect()).map(Some),
+        };
+        // A capo covering no string is no capo
+        let fretboard = capo.and_then(|capo| self.fretboard.with_capo(capo))
+            .or_else(|_| self.fretboard.with_capo(None));
+        if let Ok(fretboard) = fretboard {
+            self.set_fretboard(fretboard);
         }
     }
 
-    fn show_note_in_rect(ui: &mut Ui, note: Note, display_rect: Rect, fill_color: Color32) {
-        let note_text = note.string_representation();
+    /// 音阶选择 Root and scale from the catalog, whether to map it on the fretboard, how to label the notes
+    fn show_scale_selector(&mut self, ui: &mut Ui, cur_select_note: Option<&Note>) {
+        ui.horizontal(|ui| {
+            let root_text = self.scale_root.clone().string_representation();
+            ComboBox::from_label("Root")
+                .selected_text(&root_text)
+                .show_ui(ui, |ui| {
+                    for root in ROOT_CHOICES {
+                        let text = root.clone().string_representation();
+                        if ui.selectable_label(text == root_text, &text).clicked() {
+                            self.scale_root = root;
+                        }
+                    }
+                });
+            ComboBox::from_label("Scale")
+                .selected_text(self.scale_kind.name())
+                .show_ui(ui, |ui| {
+                    for scale_kind in SCALE_CATALOG {
+                        if ui.selectable_label(std::ptr::eq(self.scale_kind, scale_kind), scale_kind.name()).clicked() {
+                            self.scale_kind = scale_kind;
+                        }
+                    }
+                });
+            if let Some(note) = cur_select_note
+                && ui.button("Root from selected note").clicked() {
+                self.scale_root = note.note_name();
+            }
+            ui.checkbox(&mut self.show_scale_map, "Show on fretboard");
+            ComboBox::from_label("Labels")
+                .selected_text(self.label_mode.string_representation())
+                .show_ui(ui, |ui| {
+                    for label_mode in LabelMode::ALL {
+                        ui.selectable_value(&mut self.label_mode, label_mode, label_mode.string_representation());
+                    }
+                });
+        });
+    }
+
+    /// 音阶指法 One fingering of the scale at a time instead of the whole map, stepping up or down the neck
+    fn show_fingering_selector(&mut self, ui: &mut Ui, fingerings: &[ScaleFingering]) {
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Fingering")
+                .selected_text(self.fingering_kind.map_or("Whole scale", |kind| kind.string_representation()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.fingering_kind, None, "Whole scale");
+                    for kind in FingeringKind::ALL {
+                        ui.selectable_value(&mut self.fingering_kind, Some(kind), kind.string_representation());
+                    }
+                });
+            if self.fingering_kind.is_none() {
+                return;
+            }
+            if fingerings.is_empty() {
+                ui.label("None for this scale here");
+                return;
+            }
+            let fingering_cnt = fingerings.len();
+            let index = self.fingering_index.min(fingering_cnt - 1);
+            if ui.button("◀ Down").clicked() {
+                self.fingering_index = (index + fingering_cnt - 1) % fingering_cnt;
+            }
+            if ui.button("Up ▶").clicked() {
+                self.fingering_index = (index + 1) % fingering_cnt;
+            }
+            let fret_range = fingerings[index].fret_range(&self.fretboard);
+            ui.label(format!("Position {} of {}, from degree {}, frets {} to {}", index + 1, fingering_cnt,
+                             fingerings[index].start_degree(), fret_range.start(), fret_range.end()));
+        });
+    }
+
+    /// CAGED 指型 Shows one CAGED shape of the chord at a time, stepping up or down the neck
+    fn show_caged_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut is_shown = self.caged_position_index.is_some();
+            ui.checkbox(&mut is_shown, "CAGED shapes");
+            if self.caged_positions.is_empty() {
+                self.caged_position_index = None;
+                if is_shown {
+                    ui.label("Only for major and minor chords, in tunings like standard");
+                }
+                return;
+            }
+            let position_cnt = self.caged_positions.len();
+            let index = match (is_shown, self.caged_position_index) {
+                (false, _) => None,
+                (true, index) => Some(index.unwrap_or(0).min(position_cnt - 1)),
+            };
+            self.caged_position_index = index;
+            let Some(index) = index else {
+                return;
+            };
+            if ui.button("◀ Down").clicked() {
+                self.caged_position_index = Some((index + position_cnt - 1) % position_cnt);
+            }
+            if ui.button("Up ▶").clicked() {
+                self.caged_position_index = Some((index + 1) % position_cnt);
+            }
+            let position = &self.caged_positions[index];
+            ui.colored_label(caged_shape_color(position.shape()),
+                             format!("{}, frets {} to {}", position.shape().string_representation(), position.lowest_fret(), position.highest_fret()));
+        });
+    }
+
+    /// Adds the point to the chord, replacing the one on the same string, or removes it if already there.
+    fn toggle_chord_point(&mut self, point: Point) {
+        if self.chord_points.contains(&point) {
+            self.chord_points.retain(|chord_point| *chord_point != point);
+        } else {
+            self.chord_points.retain(|chord_point| chord_point.on_string() != point.on_string());
+            self.chord_points.push(point);
+        }
+    }
+
+    pub(crate) fn show_note_in_rect(ui: &mut Ui, note: Note, display_rect: Rect, fill_color: Color32) {
+        Self::show_label_in_rect(ui, &note.string_representation(), display_rect, fill_color);
+    }
+
+    /// Draws the note labelled as the selected label mode asks
+    fn show_labelled_note_in_rect(&self, ui: &mut Ui, note: &Note, display_rect: Rect, fill_color: Color32) {
+        Self::show_label_in_rect(ui, &self.label_mode.label(note, &self.scale_root), display_rect, fill_color);
+    }
+
+    fn show_label_in_rect(ui: &mut Ui, label_text: &str, display_rect: Rect, fill_color: Color32) {
         ui.painter().rect_filled(display_rect, 10, fill_color);
         ui.painter().text(display_rect.center(), Align2::CENTER_CENTER,
-                          note_text,
+                          label_text,
                           FontId::new(19.0, FontFamily::Proportional),
                           Color32::BLACK);
     }
 }
 
 impl App for FretboardApp {
+    fn save(&mut self, storage: &mut dyn Storage) {
+        if self.storage_error.is_none() {
+            storage.set_string(SAVED_STATE_KEY, SavedState::of(self.settings(), self.progress.clone()).to_ron());
+        }
+    }
+
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
         CentralPanel::default().show(ctx, |ui| {
+            let cur_select_note = self.cur_select_point
+                .and_then(|point| self.fretboard.note_of_point(&point).ok());
             let cur_string_label_text = "String:\t".to_owned()
-                + &match self.cur_select_point.get() {
+                + &match self.cur_select_point {
                 None => "None".to_owned(),
                 Some(point) => point.on_string().to_string(),
             };
             let cur_fret_label_text = "Fret:\t".to_owned()
-                + &match self.cur_select_point.get() {
+                + &match self.cur_select_point {
                 None => "None".to_owned(),
-                Some(point) => point.behind_fret().to_string(),
+                Some(point) => match (self.fretboard.capo(), self.fretboard.point_relative_to_capo(&point)) {
+                    (Some(_), Some(capo_relative_point)) if *point.behind_fret() != 0 =>
+                        format!("{} ({} from capo)", point.behind_fret(), capo_relative_point.behind_fret()),
+                    _ => point.behind_fret().to_string(),
+                },
             };
             let cur_note_label_text = "Note:\t".to_owned()
-                + &match self.cur_select_point.get() {
+                + &match &cur_select_note {
                 None => "None".to_owned(),
-                Some(point) => self
-                    .fretboard
-                    .note_of_point(&point)
-                    .string_representation(),
+                Some(note) => note.clone().string_representation(),
             };
-            let cur_note_as_root_major_scale = "Major Scale:\t".to_owned()
-                + &match self.cur_select_point.get() {
-                None => "None".to_owned(),
-                Some(point) => scale::major_scale_of(
-                    self.fretboard.note_of_point(&point).note_name())
+            let scale = self.scale_kind.of(self.scale_root.clone());
+            let scale_label_text = format!("{}:\t", scale_name(&self.scale_root, self.scale_kind))
+                + &scale.iter()
                     .map(|note_name| note_name.string_representation())
                     .map(|str| format!("{: <2}", str))
-                    .join("  "),
+                    .collect::<Vec<_>>()
+                    .join("  ");
+            let chord_label_text = "Chord:\t".to_owned()
+                + &match recognition::chords_of_points(&self.fretboard, &self.chord_points) {
+                Ok(chord_matches) if !chord_matches.is_empty() => chord_matches.iter()
+                    .take(4)
+                    .map(ChordMatch::string_representation)
+                    .collect::<Vec<_>>()
+                    .join("  |  "),
+                _ => "None".to_owned(),
             };
-            let cur_note_as_root_minor_scale = "Minor Scale:\t".to_owned()
-                + &match self.cur_select_point.get() {
-                None => "None".to_owned(),
-                Some(point) => scale::minor_scale_of(
-                    self.fretboard.note_of_point(&point).note_name())
-                    .map(|note_name| note_name.string_representation())
-                    .map(|str| format!("{: <2}", str))
-                    .join("  "),
-            };
-            let cur_note_as_root_pentatonic_major_scale = "Pentatonic Major Scale:\t".to_owned()
-                + &match self.cur_select_point.get() {
-                None => "None".to_owned(),
-                Some(point) => scale::major_pentatonic_scale_of(
-                    self.fretboard.note_of_point(&point).note_name())
-                    .map(|note_name| note_name.string_representation())
-                    .map(|str| format!("{: <2}", str))
-                    .join("  "),
-            };
-            let cur_note_as_root_pentatonic_minor_scale = "Pentatonic Minor Scale:\t".to_owned()
-                + &match self.cur_select_point.get() {
-                None => "None".to_owned(),
-                Some(point) => scale::minor_pentatonic_scale_of(
-                    self.fretboard.note_of_point(&point).note_name())
-                    .map(|note_name| note_name.string_representation())
-                    .map(|str| format!("{: <2}", str))
-                    .join("  "),
+            let chord_scales_label_text = "Scales:\t".to_owned()
+                + &match recognition::scales_of_points(&self.fretboard, &self.chord_points) {
+                Ok(scale_matches) if !scale_matches.is_empty() => scale_matches.iter()
+                    .take(4)
+                    .map(ScaleMatch::string_representation)
+                    .collect::<Vec<_>>()
+                    .join("  |  "),
+                _ => "None".to_owned(),
             };
 
             // View
             // 标题 Heading
             ui.heading("Guitar Fretboard");
+            if let Some(err) = &self.storage_error {
+                ui.colored_label(Color32::RED, format!("Settings and progress are not saved: {err}"));
+            }
+            self.show_tuning_selector(ui);
+            self.show_capo_selector(ui);
+            self.show_region_selector(ui);
+            // 模式选择 Mode selection
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.mode, AppMode::Explore, "Explore");
+                ui.selectable_value(&mut self.mode, AppMode::FindNote, "Find the note");
+                ui.selectable_value(&mut self.mode, AppMode::NameNote, "Name the note");
+            });
             // 指板显示 Fretboard view
             let (fretboard_id, fretboard_rect) = ui.allocate_space(Vec2::new(ui.available_width(), 200.0));
             let fret_board_component: FretboardComponent = FretboardComponent::new(&self.fretboard, fretboard_rect);
             ui.painter().rect_filled(fretboard_rect, 0.0, Color32::from_rgb(100, 50, 0)); // draw fretboard background: Brown
             fret_board_component.draw_fretboard_widgets(ui.painter(), fretboard_rect);
+            // 区域 Points out of the region are dimmed
+            if !self.region.is_whole() {
+                for point in self.fretboard.points() {
+                    if !self.region.contains(&self.fretboard, &point)
+                        && let Some(point_rect) = fret_board_component.get_rect_on_point(point) {
+                        ui.painter().rect_filled(point_rect, 0.0, Color32::from_black_alpha(110));
+                    }
+                }
+            }
+
+            // 找音测验 "Find the note" quiz, notes are not shown on hover
+            if self.mode == AppMode::FindNote {
+                self.find_note_quiz.show_controls(ui, &self.fretboard, &self.region, &mut self.progress);
+                let fretboard_response = ui.interact(fretboard_rect, fretboard_id, Sense::click());
+                if fretboard_response.clicked()
+                    && let Some(mouse_pos) = fretboard_response.interact_pointer_pos()
+                    && let Some((mouse_inside_point, _)) = fret_board_component.get_mouse_on_point_and_rect(mouse_pos) {
+                    self.find_note_quiz.answer(&self.fretboard, &self.region, mouse_inside_point, &mut self.progress);
+                }
+                self.find_note_quiz.show_feedback(ui, &self.fretboard, &fret_board_component);
+                return;
+            }
+
+            // 认音测验 "Name the note" quiz, answered with buttons or typing
+            if self.mode == AppMode::NameNote {
+                self.name_note_quiz.show_controls(ui, &self.fretboard, &self.region, &mut self.progress);
+                self.name_note_quiz.show_question(ui, &fret_board_component);
+                return;
+            }
+
             // 选中提示 Selection indicator
             ui.label(RichText::new(cur_string_label_text).font(FontId::new(19.0, FontFamily::Proportional)));
             ui.label(RichText::new(cur_fret_label_text).font(FontId::new(19.0, FontFamily::Proportional)));
             ui.label(RichText::new(cur_note_label_text).font(FontId::new(19.0, FontFamily::Proportional)));
-            ui.label(RichText::new(cur_note_as_root_major_scale).font(FontId::new(19.0, FontFamily::Monospace)));
-            ui.label(RichText::new(cur_note_as_root_minor_scale).font(FontId::new(19.0, FontFamily::Monospace)));
-            ui.label(RichText::new(cur_note_as_root_pentatonic_major_scale).font(FontId::new(19.0, FontFamily::Monospace)));
-            ui.label(RichText::new(cur_note_as_root_pentatonic_minor_scale).font(FontId::new(19.0, FontFamily::Monospace)));
-            let scale_map_button = ui.button("Click me");
+            let note_positions_button = ui.button("Show all positions");
+            // 音阶选择 Scale selection, shown on the fretboard
+            self.show_scale_selector(ui, cur_select_note.as_ref());
+            ui.label(RichText::new(scale_label_text).font(FontId::new(19.0, FontFamily::Monospace)));
+            let fingerings = self.fingering_kind
+                .map(|kind| kind.fingerings(&self.fretboard, &scale, self.region.frets()))
+                .unwrap_or_default();
+            self.show_fingering_selector(ui, &fingerings);
+            // 和弦识别 Chord recognition, points picked with shift-click
+            ui.label(RichText::new(chord_label_text).font(FontId::new(19.0, FontFamily::Proportional)));
+            ui.label(RichText::new(chord_scales_label_text).font(FontId::new(19.0, FontFamily::Proportional)));
+            let clear_chord_button = ui.button("Clear chord");
+            self.show_voicings(ui);
+            self.show_caged_selector(ui);
 
             // Controller
             // 音名悬浮显示、选择 Note name hover-display and selection
             let fretboard_response = ui.interact(fretboard_rect, fretboard_id, Sense::click());
-            if fretboard_response.hovered() {
-                if let Some(mouse_pos) = fretboard_response.hover_pos() {
-                    if let Some((mouse_inside_point, mouse_inside_rect)) =
-                        fret_board_component.get_mouse_on_point_and_rect(mouse_pos) {
+            if fretboard_response.hovered()
+                && let Some(mouse_pos) = fretboard_response.hover_pos()
+                && let Some((mouse_inside_point, mouse_inside_rect)) =
+                    fret_board_component.get_mouse_on_point_and_rect(mouse_pos) {
 
-                        if fretboard_response.clicked() {
-                            self.cur_select_point.set(Some(mouse_inside_point));
-                        } else {
-                            Self::show_note_in_rect(ui, self.fretboard.note_of_point(&mouse_inside_point), mouse_inside_rect, Color32::WHITE);
-                        }
-                    }
-                };
+                if fretboard_response.clicked() && ui.input(|input| input.modifiers.shift) {
+                    self.toggle_chord_point(mouse_inside_point);
+                } else if fretboard_response.clicked() {
+                    self.cur_select_point = Some(mouse_inside_point);
+                } else if let Ok(mouse_inside_note) = self.fretboard.note_of_point(&mouse_inside_point) {
+                    self.show_labelled_note_in_rect(ui, &mouse_inside_note, mouse_inside_rect, Color32::WHITE);
+                }
             }
 
-            if scale_map_button.clicked() {
-                if let Some(..) = self.cur_select_point.get() {
-                    self.show_scale_map.set(!self.show_scale_map.get());
+            if clear_chord_button.clicked() {
+                self.chord_points.clear();
+            }
+
+            if note_positions_button.clicked() && self.cur_select_point.is_some() {
+                self.show_note_positions = !self.show_note_positions;
+            }
+
+            if self.show_note_positions
+                && let Some(selected_note) = &cur_select_note {
+                for point in self.fretboard.positions_of_pitch_class_in(&selected_note.note_name(), &self.region) {
+                    let (Ok(note), Some(point_rect)) =
+                        (self.fretboard.note_of_point(&point), fret_board_component.get_rect_on_point(point)) else {
+                        continue;
+                    };
+                    // Print the note as the selected one is spelled
+                    let note = note.respell(&selected_note.note_name()).unwrap_or(note);
+                    self.show_labelled_note_in_rect(ui, &note, point_rect, Color32::GOLD);
                 }
             }
 
-            if self.show_scale_map.get() {
-                if let Some(selected_point) = self.cur_select_point.get() {
-                    let scale_points = scale_map::scale_notes_on_fretboard(
-                        &self.fretboard,
-                        scale::major_scale_of(
-                            self.fretboard.note_of_point(&selected_point).note_name()));
-                    for point in scale_points {
-                        let note = self.fretboard.note_of_point(&point);
-                        let selected_note = self.fretboard.note_of_point(&selected_point);
-                        let note_color = get_color_of_note(&note, &selected_note);
+            if self.show_scale_map {
+                let fingering = self.fingering_kind.and_then(|_| fingerings.get(self.fingering_index.min(fingerings.len().saturating_sub(1))));
+                let scale_points = match fingering {
+                    Some(fingering) => fingering.points().into_iter()
+                        .filter(|point| self.region.contains(&self.fretboard, point))
+                        .collect(),
+                    None => scale_map::scale_notes_in_region(&self.fretboard, scale.note_names(), &self.region),
+                };
+                for point in scale_points {
+                    let (Ok(fretboard_note), Some(point_rect)) =
+                        (self.fretboard.note_of_point(&point), fret_board_component.get_rect_on_point(point)) else {
+                        continue;
+                    };
+                    // Print the note as the scale spells it
+                    let note = scale.iter()
+                        .find_map(|note_name| fretboard_note.respell(&note_name))
+                        .unwrap_or(fretboard_note);
+                    let note_color = get_color_of_note(&note, &self.scale_root);
 
-                        Self::show_note_in_rect(ui, note,
-                            fret_board_component.get_rect_on_point(point).unwrap(), note_color);
+                    self.show_labelled_note_in_rect(ui, &note, point_rect, note_color);
+                }
+                // Suggested fingers in the corner
+                for fingered_point in fingering.map_or(&[][..], ScaleFingering::fingered_points) {
+                    if let Some(finger) = fingered_point.finger()
+                        && let Some(point_rect) = fret_board_component.get_rect_on_point(fingered_point.point()) {
+                        ui.painter().text(point_rect.right_top() + Vec2::new(-3.0, 1.0), Align2::RIGHT_TOP, finger.to_string(),
+                                          FontId::new(11.0, FontFamily::Proportional), Color32::DARK_BLUE);
                     }
                 }
             }
+
+            // The CAGED shape over the notes of its scale around it, colored by shape
+            if let Some(position) = self.caged_position_index.and_then(|index| self.caged_positions.get(index)) {
+                let shape_color = caged_shape_color(position.shape());
+                let chord_root = self.voicing_chord.root();
+                for (points, color) in [(position.scale_points(), shape_color.gamma_multiply(0.45)), (position.chord_points(), shape_color)] {
+                    for point in points.iter().filter(|point| self.region.contains(&self.fretboard, point)) {
+                        if let (Ok(note), Some(point_rect)) =
+                            (self.fretboard.note_of_point(point), fret_board_component.get_rect_on_point(*point)) {
+                            Self::show_label_in_rect(ui, &self.label_mode.label(&note, &chord_root), point_rect, color);
+                        }
+                    }
+                }
+            }
+
+            for point in &self.chord_points {
+                if let (Ok(note), Some(point_rect)) =
+                    (self.fretboard.note_of_point(point), fret_board_component.get_rect_on_point(*point)) {
+                    self.show_labelled_note_in_rect(ui, &note, point_rect, Color32::LIGHT_GREEN);
+                }
+            }
         });
     }
 }
 
-fn get_color_of_note(note: &Note, base_note: &Note) -> Color32 {
+fn caged_shape_color(shape: CagedShape) -> Color32 {
+    match shape {
+        CagedShape::C => Color32::from_rgb(230, 90, 80),
+        CagedShape::A => Color32::from_rgb(240, 170, 60),
+        CagedShape::G => Color32::from_rgb(120, 200, 90),
+        CagedShape::E => Color32::from_rgb(90, 160, 230),
+        CagedShape::D => Color32::from_rgb(190, 120, 220),
+    }
+}
+
+/// e.g. "C Major"
+fn scale_name(root: &NoteName, scale_kind: &ScaleKind) -> String {
+    format!("{} {}", root.clone().string_representation(), scale_kind.name())
+}
+
+fn get_color_of_note(note: &Note, root: &NoteName) -> Color32 {
     let hue = match note.octave() {
         2 => 0.,
         3 => 0.25,
@@ -167,7 +791,7 @@ fn get_color_of_note(note: &Note, base_note: &Note) -> Color32 {
         _ => 0.,
     };
 
-    let semitone_from_base = (note.minus_note(base_note).semitone_diff() % 12 + 12) % 12;
+    let semitone_from_base = (note.note_name().integer_notation() + 12 - root.integer_notation()) % 12;
     let hsva = Hsva::new(hue, (semitone_from_base + 1) as f32 / 12., 1., 1.);
     Color32::from(hsva)
 }
\ No newline at end of file